@@ -0,0 +1,131 @@
+// 下载当前平台的 pdfium 动态库到 src-tauri/pdfium/，随程序作为资源打包
+// 预编译库来自 https://github.com/bblanchon/pdfium-binaries，版本固定为 PDFIUM_TAG，
+// 与 Cargo.toml 中 pdfium-render 启用的 pdfium_<版本> 绑定一致；升级时两处一起修改
+//
+// 每个压缩包都按 pdfium-checksums.json 中记录的 SHA-256 校验，未记录或不一致时拒绝安装。
+// 升级版本后执行 `node scripts/fetch-pdfium.mjs --update-checksums` 重新生成校验值，核对后提交。
+//
+// --optional：失败时只打印警告（npm install 的 postinstall 使用，离线安装不会因此失败）
+import { execFileSync } from "node:child_process";
+import { createHash } from "node:crypto";
+import { existsSync, mkdirSync, mkdtempSync, copyFileSync, readFileSync, rmSync, writeFileSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join, dirname } from "node:path";
+import { fileURLToPath } from "node:url";
+
+const PDFIUM_TAG = "chromium/7543";
+
+const scriptDir = dirname(fileURLToPath(import.meta.url));
+const root = join(scriptDir, "..");
+const targetDir = join(root, "src-tauri", "pdfium");
+const checksumsPath = join(scriptDir, "pdfium-checksums.json");
+
+const platforms = {
+  "win32-x64": { archive: "pdfium-win-x64.tgz", library: "bin/pdfium.dll" },
+  "win32-arm64": { archive: "pdfium-win-arm64.tgz", library: "bin/pdfium.dll" },
+  "darwin-x64": { archive: "pdfium-mac-x64.tgz", library: "lib/libpdfium.dylib" },
+  "darwin-arm64": { archive: "pdfium-mac-arm64.tgz", library: "lib/libpdfium.dylib" },
+  "linux-x64": { archive: "pdfium-linux-x64.tgz", library: "lib/libpdfium.so" },
+  "linux-arm64": { archive: "pdfium-linux-arm64.tgz", library: "lib/libpdfium.so" },
+};
+
+const args = process.argv.slice(2);
+const optional = args.includes("--optional");
+
+class FetchError extends Error {}
+
+function archiveUrl(archive) {
+  return `https://github.com/bblanchon/pdfium-binaries/releases/download/${encodeURIComponent(PDFIUM_TAG)}/${archive}`;
+}
+
+async function download(archive) {
+  const url = archiveUrl(archive);
+  console.log(`下载 pdfium: ${url}`);
+
+  let response;
+  try {
+    response = await fetch(url);
+  } catch (e) {
+    throw new FetchError(`下载失败: ${e.message}`);
+  }
+  if (!response.ok) {
+    throw new FetchError(`下载失败: ${response.status} ${response.statusText}`);
+  }
+  return Buffer.from(await response.arrayBuffer());
+}
+
+function sha256(data) {
+  return createHash("sha256").update(data).digest("hex");
+}
+
+function readChecksums() {
+  const checksums = JSON.parse(readFileSync(checksumsPath, "utf8"));
+  if (checksums.tag !== PDFIUM_TAG) {
+    throw new FetchError(`${checksumsPath} 记录的版本 ${checksums.tag} 与 ${PDFIUM_TAG} 不一致`);
+  }
+  return checksums.sha256 ?? {};
+}
+
+// 下载所有平台的压缩包并重新生成校验值（升级版本时使用）
+async function updateChecksums() {
+  const sha = {};
+  for (const { archive } of Object.values(platforms)) {
+    sha[archive] = sha256(await download(archive));
+  }
+  writeFileSync(checksumsPath, JSON.stringify({ tag: PDFIUM_TAG, sha256: sha }, null, 2) + "\n");
+  console.log(`已更新 ${checksumsPath}，请核对后提交`);
+}
+
+async function fetchPdfium() {
+  const platform = platforms[`${process.platform}-${process.arch}`];
+  if (!platform) {
+    throw new FetchError(`不支持的平台: ${process.platform}-${process.arch}`);
+  }
+
+  const libraryName = platform.library.split("/").pop();
+  const target = join(targetDir, libraryName);
+  const versionFile = join(targetDir, "VERSION");
+  if (existsSync(target) && existsSync(versionFile) && readFileSync(versionFile, "utf8").trim() === PDFIUM_TAG) {
+    return;
+  }
+
+  const expected = readChecksums()[platform.archive];
+  if (!expected) {
+    throw new FetchError(`${checksumsPath} 中没有 ${platform.archive} 的校验值`);
+  }
+
+  const data = await download(platform.archive);
+  const actual = sha256(data);
+  if (actual !== expected) {
+    throw new FetchError(`${platform.archive} 校验失败: 期望 ${expected}，实际 ${actual}`);
+  }
+
+  const workDir = mkdtempSync(join(tmpdir(), "pdfium-"));
+  try {
+    const archivePath = join(workDir, platform.archive);
+    writeFileSync(archivePath, data);
+    execFileSync("tar", ["-xzf", archivePath, "-C", workDir, platform.library]);
+
+    mkdirSync(targetDir, { recursive: true });
+    copyFileSync(join(workDir, platform.library), target);
+    writeFileSync(versionFile, PDFIUM_TAG + "\n");
+    console.log(`已保存到 ${target}`);
+  } finally {
+    rmSync(workDir, { recursive: true, force: true });
+  }
+}
+
+try {
+  if (args.includes("--update-checksums")) {
+    await updateChecksums();
+  } else {
+    await fetchPdfium();
+  }
+} catch (e) {
+  if (optional) {
+    console.warn(`警告：${e.message}；PDF 功能需要 pdfium，可稍后执行 npm run fetch-pdfium`);
+  } else {
+    console.error(e instanceof FetchError ? e.message : e);
+    process.exit(1);
+  }
+}
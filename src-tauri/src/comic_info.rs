@@ -0,0 +1,438 @@
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::comic_source::PageInfo;
+use crate::error::ComicError;
+use crate::i18n::{tr, Msg};
+
+/// ComicInfo.xml 文件名（ComicRack/Komga 约定）
+pub const COMIC_INFO_FILE: &str = "ComicInfo.xml";
+
+/// 页面类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComicPageType {
+    FrontCover,
+    InnerCover,
+    Roundup,
+    Story,
+    Advertisement,
+    Editorial,
+    Letters,
+    Preview,
+    BackCover,
+    Other,
+    Deleted,
+}
+
+impl ComicPageType {
+    /// 从 ComicInfo 中的 Type 属性解析，未知类型视为 Other
+    fn parse(value: &str) -> Self {
+        match value {
+            "FrontCover" => Self::FrontCover,
+            "InnerCover" => Self::InnerCover,
+            "Roundup" => Self::Roundup,
+            "Story" => Self::Story,
+            "Advertisement" => Self::Advertisement,
+            "Editorial" => Self::Editorial,
+            "Letters" => Self::Letters,
+            "Preview" => Self::Preview,
+            "BackCover" => Self::BackCover,
+            "Deleted" => Self::Deleted,
+            _ => Self::Other,
+        }
+    }
+
+    /// 写回 ComicInfo 时使用的 Type 属性值
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::FrontCover => "FrontCover",
+            Self::InnerCover => "InnerCover",
+            Self::Roundup => "Roundup",
+            Self::Story => "Story",
+            Self::Advertisement => "Advertisement",
+            Self::Editorial => "Editorial",
+            Self::Letters => "Letters",
+            Self::Preview => "Preview",
+            Self::BackCover => "BackCover",
+            Self::Other => "Other",
+            Self::Deleted => "Deleted",
+        }
+    }
+}
+
+/// ComicInfo 中的页面描述
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComicInfoPage {
+    /// 对应图片在自然排序列表中的索引
+    pub image: usize,
+    #[serde(rename = "type")]
+    pub page_type: ComicPageType,
+    #[serde(rename = "doublePage")]
+    pub double_page: bool,
+}
+
+/// ComicInfo.xml 元数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComicInfo {
+    pub series: Option<String>,
+    pub number: Option<String>,
+    pub volume: Option<i32>,
+    pub title: Option<String>,
+    pub writer: Option<String>,
+    pub summary: Option<String>,
+    pub manga: Option<String>, // "Unknown" | "No" | "Yes" | "YesAndRightToLeft"
+    #[serde(rename = "rightToLeft")]
+    pub right_to_left: bool,
+    #[serde(default)]
+    pub pages: Vec<ComicInfoPage>,
+}
+
+/// 检查文件名是否为 ComicInfo.xml（不区分大小写）
+pub fn is_comic_info_file(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().eq_ignore_ascii_case(COMIC_INFO_FILE))
+        .unwrap_or(false)
+}
+
+/// 读取子元素文本
+fn child_text(node: Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// 解析 ComicInfo.xml
+pub fn parse_comic_info(text: &str) -> Result<ComicInfo, ComicError> {
+    let doc = Document::parse(text).map_err(ComicError::metadata)?;
+    let root = doc.root_element();
+
+    if root.tag_name().name() != "ComicInfo" {
+        return Err(ComicError::metadata(tr(Msg::MissingComicInfoRoot, &[])));
+    }
+
+    let manga = child_text(root, "Manga");
+    let right_to_left = manga.as_deref() == Some("YesAndRightToLeft");
+
+    let mut pages = Vec::new();
+    if let Some(pages_node) = root.children().find(|n| n.tag_name().name() == "Pages") {
+        for page in pages_node.children().filter(|n| n.tag_name().name() == "Page") {
+            let Some(image) = page.attribute("Image").and_then(|v| v.trim().parse().ok()) else {
+                continue;
+            };
+
+            pages.push(ComicInfoPage {
+                image,
+                page_type: ComicPageType::parse(page.attribute("Type").unwrap_or("Story")),
+                double_page: page
+                    .attribute("DoublePage")
+                    .map(|v| v.eq_ignore_ascii_case("true"))
+                    .unwrap_or(false),
+            });
+        }
+    }
+
+    Ok(ComicInfo {
+        series: child_text(root, "Series"),
+        number: child_text(root, "Number"),
+        volume: child_text(root, "Volume").and_then(|v| v.parse().ok()),
+        title: child_text(root, "Title"),
+        writer: child_text(root, "Writer"),
+        summary: child_text(root, "Summary"),
+        manga,
+        right_to_left,
+        pages,
+    })
+}
+
+/// 由本结构维护的元素，写回时重新生成，其余元素原样保留
+const MANAGED_ELEMENTS: [&str; 8] = ["Series", "Number", "Volume", "Title", "Writer", "Summary", "Manga", "Pages"];
+
+/// 由本结构维护的 Page 属性，其余属性（ImageWidth、ImageSize、Key、Bookmark 等）写回时保留
+const MANAGED_PAGE_ATTRIBUTES: [&str; 3] = ["Image", "Type", "DoublePage"];
+
+/// 原有 ComicInfo.xml 中每个 Page 的图片索引和未维护的属性
+fn original_page_attributes(root: Node) -> Vec<(usize, Vec<(String, String)>)> {
+    let Some(pages_node) = root.children().find(|n| n.tag_name().name() == "Pages") else {
+        return Vec::new();
+    };
+
+    pages_node
+        .children()
+        .filter(|n| n.tag_name().name() == "Page")
+        .filter_map(|page| {
+            let image = page.attribute("Image")?.trim().parse().ok()?;
+            let extra = page
+                .attributes()
+                .filter(|a| a.namespace().is_none() && !MANAGED_PAGE_ATTRIBUTES.contains(&a.name()))
+                .map(|a| (a.name().to_string(), a.value().to_string()))
+                .collect();
+            Some((image, extra))
+        })
+        .collect()
+}
+
+/// 转义 XML 文本和属性值
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 将 ComicInfo 序列化为 XML
+/// 传入原有的 ComicInfo.xml 时，保留其中未由本结构维护的元素（如 Genre、Publisher）
+pub fn serialize_comic_info(info: &ComicInfo, original: Option<&str>) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+    );
+
+    let mut push_element = |name: &str, value: Option<&str>| {
+        if let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) {
+            xml.push_str(&format!("  <{name}>{}</{name}>\n", escape_xml(value)));
+        }
+    };
+
+    // Manga 字段与阅读方向保持一致
+    let manga = match (info.manga.as_deref(), info.right_to_left) {
+        (_, true) => Some("YesAndRightToLeft"),
+        (Some("YesAndRightToLeft"), false) => Some("Yes"),
+        (manga, false) => manga,
+    };
+
+    push_element("Series", info.series.as_deref());
+    push_element("Number", info.number.as_deref());
+    push_element("Volume", info.volume.map(|v| v.to_string()).as_deref());
+    push_element("Title", info.title.as_deref());
+    push_element("Writer", info.writer.as_deref());
+    push_element("Summary", info.summary.as_deref());
+    push_element("Manga", manga);
+
+    // 原样保留其他元素
+    let mut original_pages = Vec::new();
+    if let Some(doc) = original.and_then(|text| Document::parse(text).ok()) {
+        let root = doc.root_element();
+        if root.tag_name().name() == "ComicInfo" {
+            original_pages = original_page_attributes(root);
+            for node in root.children().filter(|n| n.is_element()) {
+                if !MANAGED_ELEMENTS.contains(&node.tag_name().name()) {
+                    xml.push_str("  ");
+                    xml.push_str(&doc.input_text()[node.range()]);
+                    xml.push('\n');
+                }
+            }
+        }
+    }
+
+    if !info.pages.is_empty() {
+        xml.push_str("  <Pages>\n");
+        for page in &info.pages {
+            xml.push_str(&format!("    <Page Image=\"{}\" Type=\"{}\"", page.image, page.page_type.as_str()));
+            if page.double_page {
+                xml.push_str(" DoublePage=\"true\"");
+            }
+            // 合并原有 Page 中的其他属性（同一图片只对应一次）
+            if let Some(position) = original_pages.iter().position(|(image, _)| *image == page.image) {
+                let (_, extra) = original_pages.remove(position);
+                for (name, value) in extra {
+                    xml.push_str(&format!(" {}=\"{}\"", name, escape_xml(&value)));
+                }
+            }
+            xml.push_str(" />\n");
+        }
+        xml.push_str("  </Pages>\n");
+    }
+
+    xml.push_str("</ComicInfo>\n");
+    xml
+}
+
+/// 按 ComicInfo 的页面描述调整页面列表
+/// 按 Pages 中的顺序排列、移除 Deleted 页面并附加页面类型，未描述的页面保持原顺序追加在末尾
+pub fn apply_comic_info(pages: Vec<PageInfo>, info: &ComicInfo) -> Vec<PageInfo> {
+    if info.pages.is_empty() {
+        return pages;
+    }
+
+    let mut used = vec![false; pages.len()];
+    let mut ordered = Vec::with_capacity(pages.len());
+
+    for info_page in &info.pages {
+        if info_page.image >= pages.len() || used[info_page.image] {
+            continue;
+        }
+        used[info_page.image] = true;
+
+        if info_page.page_type == ComicPageType::Deleted {
+            continue;
+        }
+
+        let mut page = pages[info_page.image].clone();
+        page.page_type = Some(info_page.page_type);
+        page.double_page = info_page.double_page;
+        ordered.push(page);
+    }
+
+    ordered.extend(
+        pages
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| !used[*i])
+            .map(|(_, page)| page),
+    );
+
+    // 重新分配索引
+    for (i, page) in ordered.iter_mut().enumerate() {
+        page.index = i;
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<ComicInfo>
+  <Series>Old</Series>
+  <Genre>Action</Genre>
+  <Pages>
+    <Page Image="0" Type="FrontCover" ImageWidth="1200" ImageHeight="1800" ImageSize="345678" Key="abc" />
+    <Page Image="1" ImageWidth="2400" ImageHeight="1800" Bookmark="Chapter &amp; 1" />
+    <Page Image="2" Type="Deleted" />
+  </Pages>
+</ComicInfo>"#;
+
+    #[test]
+    fn serialize_keeps_unknown_page_attributes() {
+        let mut info = parse_comic_info(ORIGINAL).unwrap();
+        info.series = Some("New".to_string());
+        info.pages[1].double_page = true;
+        info.pages[1].page_type = ComicPageType::Story;
+
+        let xml = serialize_comic_info(&info, Some(ORIGINAL));
+        let doc = Document::parse(&xml).unwrap();
+        let root = doc.root_element();
+
+        assert_eq!(child_text(root, "Series").as_deref(), Some("New"));
+        assert_eq!(child_text(root, "Genre").as_deref(), Some("Action"));
+
+        let pages: Vec<Node> = root
+            .children()
+            .find(|n| n.tag_name().name() == "Pages")
+            .unwrap()
+            .children()
+            .filter(|n| n.tag_name().name() == "Page")
+            .collect();
+        assert_eq!(pages.len(), 3);
+
+        assert_eq!(pages[0].attribute("Type"), Some("FrontCover"));
+        assert_eq!(pages[0].attribute("ImageWidth"), Some("1200"));
+        assert_eq!(pages[0].attribute("ImageSize"), Some("345678"));
+        assert_eq!(pages[0].attribute("Key"), Some("abc"));
+
+        assert_eq!(pages[1].attribute("DoublePage"), Some("true"));
+        assert_eq!(pages[1].attribute("ImageWidth"), Some("2400"));
+        assert_eq!(pages[1].attribute("Bookmark"), Some("Chapter & 1"));
+
+        assert_eq!(pages[2].attribute("Type"), Some("Deleted"));
+    }
+
+    #[test]
+    fn serialize_without_original_writes_managed_attributes_only() {
+        let info = ComicInfo {
+            pages: vec![ComicInfoPage {
+                image: 0,
+                page_type: ComicPageType::FrontCover,
+                double_page: false,
+            }],
+            ..Default::default()
+        };
+
+        let xml = serialize_comic_info(&info, None);
+        assert!(xml.contains(r#"<Page Image="0" Type="FrontCover" />"#));
+    }
+
+    fn page_infos(count: usize) -> Vec<PageInfo> {
+        (0..count)
+            .map(|index| PageInfo {
+                index,
+                name: format!("{:03}.jpg", index),
+                path: format!("{:03}.jpg", index),
+                size: 0,
+                page_type: None,
+                double_page: false,
+            })
+            .collect()
+    }
+
+    fn info_page(image: usize, page_type: ComicPageType, double_page: bool) -> ComicInfoPage {
+        ComicInfoPage {
+            image,
+            page_type,
+            double_page,
+        }
+    }
+
+    #[test]
+    fn apply_without_pages_keeps_the_natural_order() {
+        let pages = apply_comic_info(page_infos(3), &ComicInfo::default());
+        let names: Vec<&str> = pages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["000.jpg", "001.jpg", "002.jpg"]);
+        assert!(pages.iter().all(|p| p.page_type.is_none()));
+    }
+
+    #[test]
+    fn apply_drops_deleted_pages_and_reindexes() {
+        let info = ComicInfo {
+            pages: vec![
+                info_page(0, ComicPageType::FrontCover, false),
+                info_page(1, ComicPageType::Deleted, false),
+                info_page(2, ComicPageType::Story, true),
+            ],
+            ..Default::default()
+        };
+
+        let pages = apply_comic_info(page_infos(4), &info);
+        let names: Vec<&str> = pages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["000.jpg", "002.jpg", "003.jpg"]);
+        assert_eq!(pages.iter().map(|p| p.index).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(pages[0].page_type, Some(ComicPageType::FrontCover));
+        assert!(pages[1].double_page);
+        // 未描述的页面不附加类型
+        assert_eq!(pages[2].page_type, None);
+    }
+
+    #[test]
+    fn apply_follows_the_declared_order_and_appends_the_rest() {
+        let info = ComicInfo {
+            pages: vec![
+                info_page(3, ComicPageType::FrontCover, false),
+                info_page(1, ComicPageType::Story, false),
+                // 重复和越界的描述被忽略
+                info_page(3, ComicPageType::BackCover, false),
+                info_page(9, ComicPageType::Story, false),
+            ],
+            ..Default::default()
+        };
+
+        let pages = apply_comic_info(page_infos(5), &info);
+        let names: Vec<&str> = pages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["003.jpg", "001.jpg", "000.jpg", "002.jpg", "004.jpg"]);
+        assert_eq!(pages[0].page_type, Some(ComicPageType::FrontCover));
+        assert_eq!(pages.iter().map(|p| p.index).collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,659 @@
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::comic_info::{ComicInfo, ComicPageType};
+use crate::epub_handler::EpubSource;
+use crate::error::ComicError;
+use crate::file_system::{is_image_file, ArchiveKind, DirectorySource};
+use crate::image_handler::{get_image_dimensions_from_bytes, scale_image, ScaleFilter};
+use crate::page_cache::{PageCache, PageKey};
+use crate::pdf_handler::PdfSource;
+use crate::rar_handler::RarSource;
+use crate::sevenz_handler::SevenZSource;
+use crate::tar_handler::TarSource;
+use crate::zip_handler::{write_zip_comic_info, ZipSource};
+
+/// 页面信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageInfo {
+    pub index: usize,
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    /// ComicInfo 中声明的页面类型
+    #[serde(rename = "pageType")]
+    pub page_type: Option<ComicPageType>,
+    #[serde(rename = "doublePage")]
+    pub double_page: bool,
+}
+
+/// 漫画元数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComicMetadata {
+    pub path: String,
+    pub name: String,
+    #[serde(rename = "archiveKind")]
+    pub archive_kind: Option<ArchiveKind>,
+    #[serde(rename = "pageCount")]
+    pub page_count: usize,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// 是否为从右到左的阅读顺序（日漫）
+    #[serde(rename = "rightToLeft")]
+    pub right_to_left: bool,
+    #[serde(rename = "comicInfo")]
+    pub comic_info: Option<ComicInfo>,
+}
+
+/// 打开漫画的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenedComic {
+    pub metadata: ComicMetadata,
+    pub pages: Vec<PageInfo>,
+}
+
+/// 漫画来源：文件夹和各类压缩包的统一抽象
+/// 新增格式只需实现此 trait，并在 `open_source` 中注册
+pub trait ComicSource: Send {
+    /// 漫画路径
+    fn path(&self) -> &str;
+
+    /// 压缩包类型，文件夹返回 None
+    fn archive_kind(&self) -> Option<ArchiveKind>;
+
+    /// 按阅读顺序排列的页面列表
+    fn pages(&self) -> &[PageInfo];
+
+    /// 读取页面的二进制数据
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError>;
+
+    /// 获取页面尺寸，默认读取整页数据后解析
+    fn page_dimensions(&mut self, page: &PageInfo) -> Result<(u32, u32), ComicError> {
+        let buffer = self.read_page(page)?;
+        get_image_dimensions_from_bytes(buffer)
+    }
+
+    /// 批量获取所有页面尺寸，失败的页面为 None
+    /// 默认逐页调用 page_dimensions，只能顺序读取的格式可覆盖为单次遍历
+    fn all_page_dimensions(&mut self) -> Vec<Option<(u32, u32)>> {
+        let pages = self.pages().to_vec();
+        pages.iter().map(|page| self.page_dimensions(page).ok()).collect()
+    }
+
+    /// 按指定宽度或 DPI 渲染页面，只有 PDF 支持，其他格式返回 None
+    fn render_page(
+        &mut self,
+        _page: &PageInfo,
+        _target_width: Option<u32>,
+        _dpi: Option<f32>,
+    ) -> Option<Result<Vec<u8>, ComicError>> {
+        None
+    }
+
+    /// 漫画自带的 ComicInfo.xml，不支持或不存在时返回 None
+    fn comic_info(&self) -> Option<&ComicInfo> {
+        None
+    }
+
+    /// 获取漫画元数据
+    fn metadata(&mut self) -> Result<ComicMetadata, ComicError> {
+        let path = self.path().to_string();
+        let name = Path::new(&path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+        let comic_info = self.comic_info().cloned();
+
+        Ok(ComicMetadata {
+            path,
+            name,
+            archive_kind: self.archive_kind(),
+            page_count: self.pages().len(),
+            title: comic_info.as_ref().and_then(|info| info.title.clone()),
+            author: comic_info.as_ref().and_then(|info| info.writer.clone()),
+            right_to_left: comic_info.as_ref().map(|info| info.right_to_left).unwrap_or(false),
+            comic_info,
+        })
+    }
+}
+
+/// 每个漫画最多同时打开的句柄数（同一压缩包内的页面可并行解压）
+const MAX_HANDLES_PER_SOURCE: usize = 4;
+
+/// 缩放后页面的 JPEG 质量
+const SCALED_PAGE_QUALITY: u8 = 90;
+
+/// 句柄池状态
+struct PoolState {
+    idle: Vec<Box<dyn ComicSource>>,
+    /// 已打开的句柄总数（空闲 + 使用中）
+    total: usize,
+    /// 已从缓存移除，不再借出新句柄
+    closed: bool,
+    /// 页面列表（同一文件的各句柄相同），首次打开句柄时记录，之后查找页面无需借出句柄
+    pages: Option<Arc<Vec<PageInfo>>>,
+}
+
+/// 单个漫画的来源句柄池：每个句柄独立加锁，读取不会阻塞其他页面或其他漫画
+pub struct SourcePool {
+    path: String,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+impl SourcePool {
+    fn new(path: &str, initial: Option<Box<dyn ComicSource>>) -> Self {
+        let total = initial.is_some() as usize;
+        let pages = initial.as_ref().map(|source| Arc::new(source.pages().to_vec()));
+        Self {
+            path: path.to_string(),
+            state: Mutex::new(PoolState {
+                idle: initial.into_iter().collect(),
+                total,
+                closed: false,
+                pages,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// 借出一个句柄：优先复用空闲句柄，未达上限时打开新句柄，否则等待归还
+    fn checkout(&self) -> Result<PooledSource<'_>, ComicError> {
+        let mut state = self.state.lock()?;
+
+        loop {
+            if state.closed {
+                return Err(ComicError::ComicClosed { path: self.path.clone() });
+            }
+            if let Some(source) = state.idle.pop() {
+                return Ok(PooledSource { pool: self, source: Some(source) });
+            }
+            if state.total < MAX_HANDLES_PER_SOURCE {
+                state.total += 1;
+                break;
+            }
+            state = self.available.wait(state)?;
+        }
+        drop(state);
+
+        // 打开新句柄时不持有池锁
+        match open_source(&self.path) {
+            Ok(source) => {
+                if let Ok(mut state) = self.state.lock() {
+                    state.pages.get_or_insert_with(|| Arc::new(source.pages().to_vec()));
+                }
+                Ok(PooledSource { pool: self, source: Some(source) })
+            }
+            Err(e) => {
+                if let Ok(mut state) = self.state.lock() {
+                    state.total -= 1;
+                }
+                self.available.notify_all();
+                Err(e)
+            }
+        }
+    }
+
+    /// 页面列表；尚未打开过句柄时借出一个句柄读取
+    fn pages(&self) -> Result<Arc<Vec<PageInfo>>, ComicError> {
+        if let Some(pages) = &self.state.lock()?.pages {
+            return Ok(pages.clone());
+        }
+        let mut source = self.checkout()?;
+        Ok(Arc::new(source.get().pages().to_vec()))
+    }
+
+    /// 按索引查找页面，不借出句柄
+    fn page(&self, page_index: usize) -> Result<PageInfo, ComicError> {
+        self.pages()?
+            .get(page_index)
+            .cloned()
+            .ok_or_else(|| ComicError::PageOutOfRange {
+                path: self.path.clone(),
+                index: page_index,
+            })
+    }
+
+    /// 读取页面原图：先查内存缓存，未命中时才借出句柄读取并写入缓存
+    fn read_page(&self, page: &PageInfo, key: PageKey, cache: &PageCache) -> Result<Vec<u8>, ComicError> {
+        if let Some(buffer) = cache.get(&key) {
+            return Ok(buffer.to_vec());
+        }
+        let buffer = self.checkout()?.get().read_page(page)?;
+        cache.insert(key, Arc::new(buffer.clone()));
+        Ok(buffer)
+    }
+
+    /// 归还句柄；池已关闭时直接释放
+    fn checkin(&self, source: Box<dyn ComicSource>) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.closed {
+                state.total -= 1;
+                drop(source);
+            } else {
+                state.idle.push(source);
+            }
+        }
+        self.available.notify_all();
+    }
+
+    /// 关闭句柄池并等待使用中的句柄全部归还（用于改写文件前释放文件句柄）
+    fn close(&self) -> Result<(), ComicError> {
+        let mut state = self.state.lock()?;
+        state.closed = true;
+        state.total -= state.idle.len();
+        state.idle.clear();
+        self.available.notify_all();
+
+        while state.total > 0 {
+            state = self.available.wait(state)?;
+        }
+        Ok(())
+    }
+}
+
+/// 借出的句柄，离开作用域时自动归还
+struct PooledSource<'a> {
+    pool: &'a SourcePool,
+    source: Option<Box<dyn ComicSource>>,
+}
+
+impl PooledSource<'_> {
+    fn get(&mut self) -> &mut dyn ComicSource {
+        self.source.as_mut().unwrap().as_mut()
+    }
+}
+
+impl Drop for PooledSource<'_> {
+    fn drop(&mut self) {
+        if let Some(source) = self.source.take() {
+            self.pool.checkin(source);
+        }
+    }
+}
+
+/// 漫画来源缓存：使用 LRU 缓存保留最近使用的 5 个漫画的句柄池
+/// 全局锁只用于查找句柄池，解压和读取都在各自的句柄上进行
+pub struct SourceCache {
+    pools: Mutex<LruCache<String, Arc<SourcePool>>>,
+    /// 解压后的页面数据，翻回已读页面时直接从内存返回
+    pub pages: PageCache,
+}
+
+impl Default for SourceCache {
+    fn default() -> Self {
+        Self {
+            pools: Mutex::new(LruCache::new(NonZeroUsize::new(5).unwrap())),
+            pages: PageCache::default(),
+        }
+    }
+}
+
+/// 从条目（路径, 大小）中筛选图片页面
+/// 跳过隐藏文件和 __MACOSX，按自然排序后重新分配索引，各来源共用
+pub fn build_page_list<I>(entries: I) -> Vec<PageInfo>
+where
+    I: IntoIterator<Item = (String, u64)>,
+{
+    let mut pages: Vec<PageInfo> = Vec::new();
+
+    for (file_path, size) in entries {
+        let file_path_obj = Path::new(&file_path);
+
+        // 跳过隐藏文件
+        if let Some(name) = file_path_obj.file_name() {
+            let name_str = name.to_string_lossy().to_string();
+            if name_str.starts_with('.') || name_str.starts_with("__MACOSX") {
+                continue;
+            }
+        }
+
+        // 检查是否为图片文件
+        if is_image_file(file_path_obj) {
+            let name = file_path_obj
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| file_path.clone());
+
+            pages.push(PageInfo {
+                index: pages.len(),
+                name,
+                path: file_path,
+                size,
+                page_type: None,
+                double_page: false,
+            });
+        }
+    }
+
+    // 自然排序
+    pages.sort_by(|a, b| natord::compare(&a.name, &b.name));
+
+    // 重新分配索引
+    for (i, page) in pages.iter_mut().enumerate() {
+        page.index = i;
+    }
+
+    pages
+}
+
+/// 根据路径打开对应的漫画来源
+pub fn open_source(comic_path: &str) -> Result<Box<dyn ComicSource>, ComicError> {
+    let path = Path::new(comic_path);
+
+    if !path.exists() {
+        return Err(ComicError::not_found(comic_path));
+    }
+
+    if path.is_dir() {
+        return Ok(Box::new(DirectorySource::open(comic_path)?));
+    }
+
+    match ArchiveKind::from_path(path) {
+        Some(ArchiveKind::Zip) => Ok(Box::new(ZipSource::open(comic_path)?)),
+        Some(ArchiveKind::Rar) => Ok(Box::new(RarSource::open(comic_path)?)),
+        Some(ArchiveKind::SevenZip) => Ok(Box::new(SevenZSource::open(comic_path)?)),
+        Some(ArchiveKind::Tar) => Ok(Box::new(TarSource::open(comic_path)?)),
+        Some(ArchiveKind::Pdf) => Ok(Box::new(PdfSource::open(comic_path)?)),
+        Some(ArchiveKind::Epub) => Ok(Box::new(EpubSource::open(comic_path)?)),
+        None => Err(ComicError::UnsupportedFormat {
+            path: comic_path.to_string(),
+        }),
+    }
+}
+
+/// 从 LRU 缓存获取漫画的句柄池，不存在则创建（句柄在首次借出时才打开）
+fn get_pool(comic_path: &str, cache: &SourceCache) -> Result<Arc<SourcePool>, ComicError> {
+    let mut guard = cache.pools.lock()?;
+
+    // 从 LRU 缓存获取（会自动更新访问顺序）
+    if let Some(pool) = guard.get(comic_path) {
+        return Ok(pool.clone());
+    }
+
+    let pool = Arc::new(SourcePool::new(comic_path, None));
+    guard.put(comic_path.to_string(), pool.clone());
+    Ok(pool)
+}
+
+/// 借出漫画来源的一个句柄执行操作，不持有全局缓存锁
+fn with_source<T>(
+    comic_path: &str,
+    cache: &SourceCache,
+    f: impl FnOnce(&mut dyn ComicSource) -> Result<T, ComicError>,
+) -> Result<T, ComicError> {
+    let pool = get_pool(comic_path, cache)?;
+    let mut source = pool.checkout()?;
+    f(source.get())
+}
+
+/// 按索引查找页面
+fn find_page(source: &dyn ComicSource, page_index: usize) -> Result<PageInfo, ComicError> {
+    source
+        .pages()
+        .get(page_index)
+        .cloned()
+        .ok_or_else(|| ComicError::PageOutOfRange {
+            path: source.path().to_string(),
+            index: page_index,
+        })
+}
+
+/// 打开漫画：重新扫描页面列表并刷新缓存
+pub fn open_comic(comic_path: &str, cache: &SourceCache) -> Result<OpenedComic, ComicError> {
+    let mut source = open_source(comic_path)?;
+    let metadata = source.metadata()?;
+    let pages = source.pages().to_vec();
+
+    // 新句柄池替换旧的，旧池中使用中的句柄归还后随之释放
+    let pool = Arc::new(SourcePool::new(comic_path, Some(source)));
+    let mut guard = cache.pools.lock()?;
+    guard.put(comic_path.to_string(), pool);
+
+    Ok(OpenedComic { metadata, pages })
+}
+
+/// 读取页面的二进制数据（用于 Blob URL）
+pub fn read_page(comic_path: &str, page_index: usize, cache: &SourceCache) -> Result<Vec<u8>, ComicError> {
+    read_page_entry(comic_path, page_index, cache).map(|(_, buffer)| buffer)
+}
+
+/// 读取页面信息和二进制数据（页面名用于判断 MIME 类型）
+/// 命中内存缓存时不借出句柄
+pub fn read_page_entry(comic_path: &str, page_index: usize, cache: &SourceCache) -> Result<(PageInfo, Vec<u8>), ComicError> {
+    let pool = get_pool(comic_path, cache)?;
+    let page = pool.page(page_index)?;
+    let key = PageKey::new(comic_path, &page.path);
+    let buffer = pool.read_page(&page, key, &cache.pages)?;
+    Ok((page, buffer))
+}
+
+/// 按指定宽度或 DPI 渲染页面（PDF），使用句柄池中已打开的文档
+pub fn render_page(
+    comic_path: &str,
+    page_index: usize,
+    target_width: Option<u32>,
+    dpi: Option<f32>,
+    cache: &SourceCache,
+) -> Result<Vec<u8>, ComicError> {
+    with_source(comic_path, cache, |source| {
+        let page = find_page(source, page_index)?;
+        source
+            .render_page(&page, target_width, dpi)
+            .unwrap_or_else(|| Err(ComicError::UnsupportedFormat { path: comic_path.to_string() }))
+    })
+}
+
+/// 读取按目标宽度缩小后的页面，结果按尺寸和滤镜分别缓存；原图不够宽时返回原图
+pub fn read_page_scaled(
+    comic_path: &str,
+    page_index: usize,
+    target_width: u32,
+    filter: ScaleFilter,
+    cache: &SourceCache,
+) -> Result<Vec<u8>, ComicError> {
+    let pool = get_pool(comic_path, cache)?;
+    let page = pool.page(page_index)?;
+    let original_key = PageKey::new(comic_path, &page.path);
+    let key = original_key
+        .clone()
+        .with_variant(format!("{}:{:?}", target_width, filter));
+
+    if let Some(buffer) = cache.pages.get(&key) {
+        return Ok(buffer.to_vec());
+    }
+
+    // 在句柄之外解码缩放，不占用压缩包句柄
+    let original = pool.read_page(&page, original_key, &cache.pages)?;
+    let buffer = match scale_image(&original, target_width, filter, SCALED_PAGE_QUALITY)? {
+        Some(scaled) => scaled,
+        None => original,
+    };
+    cache.pages.insert(key, Arc::new(buffer.clone()));
+    Ok(buffer)
+}
+
+/// 预读页面到内存缓存，已缓存或越界的页面直接跳过
+pub fn prefetch_page(comic_path: &str, page_index: usize, cache: &SourceCache) -> Result<(), ComicError> {
+    let pool = get_pool(comic_path, cache)?;
+    let Some(page) = pool.pages()?.get(page_index).cloned() else {
+        return Ok(());
+    };
+    let key = PageKey::new(comic_path, &page.path);
+
+    if !cache.pages.contains(&key) {
+        let buffer = pool.checkout()?.get().read_page(&page)?;
+        cache.pages.insert(key, Arc::new(buffer));
+    }
+    Ok(())
+}
+
+/// 获取页面列表
+pub fn get_pages(comic_path: &str, cache: &SourceCache) -> Result<Vec<PageInfo>, ComicError> {
+    Ok(get_pool(comic_path, cache)?.pages()?.to_vec())
+}
+
+/// 获取页面信息（不借出句柄）
+pub fn get_page_info(comic_path: &str, page_index: usize, cache: &SourceCache) -> Result<PageInfo, ComicError> {
+    get_pool(comic_path, cache)?.page(page_index)
+}
+
+/// 获取漫画元数据（包括 ComicInfo.xml）
+pub fn get_comic_metadata(comic_path: &str, cache: &SourceCache) -> Result<ComicMetadata, ComicError> {
+    with_source(comic_path, cache, |source| source.metadata())
+}
+
+/// 获取封面页索引：优先使用 ComicInfo 标记的 FrontCover，否则为第一页
+pub fn get_cover_page_index(comic_path: &str, cache: &SourceCache) -> Result<usize, ComicError> {
+    with_source(comic_path, cache, |source| {
+        let pages = source.pages();
+        if pages.is_empty() {
+            return Err(ComicError::EmptyComic {
+                path: comic_path.to_string(),
+            });
+        }
+
+        Ok(pages
+            .iter()
+            .find(|page| page.page_type == Some(ComicPageType::FrontCover))
+            .map(|page| page.index)
+            .unwrap_or(0))
+    })
+}
+
+/// 批量获取所有页面尺寸（按页面顺序）
+pub fn get_all_page_dimensions(comic_path: &str, cache: &SourceCache) -> Result<Vec<Option<(u32, u32)>>, ComicError> {
+    with_source(comic_path, cache, |source| Ok(source.all_page_dimensions()))
+}
+
+/// 按条目路径建立页面索引，供单次遍历的格式使用
+pub fn page_positions(pages: &[PageInfo]) -> HashMap<&str, usize> {
+    pages.iter().enumerate().map(|(i, page)| (page.path.as_str(), i)).collect()
+}
+
+/// 获取页面尺寸
+pub fn get_page_dimensions(comic_path: &str, page_index: usize, cache: &SourceCache) -> Result<(u32, u32), ComicError> {
+    with_source(comic_path, cache, |source| {
+        let page = find_page(source, page_index)?;
+        source.page_dimensions(&page)
+    })
+}
+
+/// 写入 ComicInfo.xml 并重新打开漫画
+/// 目前仅支持 ZIP/CBZ；只在取出句柄池时短暂持有缓存锁，等待读取结束和改写文件期间不阻塞其他漫画
+pub fn save_comic_info(comic_path: &str, info: &ComicInfo, cache: &SourceCache) -> Result<OpenedComic, ComicError> {
+    if ArchiveKind::from_path(Path::new(comic_path)) != Some(ArchiveKind::Zip) {
+        return Err(ComicError::ReadOnlyFormat {
+            path: comic_path.to_string(),
+        });
+    }
+
+    // 先移除缓存中的句柄池，等待正在进行的读取结束并释放旧文件句柄
+    let pool = cache.pools.lock()?.pop(comic_path);
+    if let Some(pool) = pool {
+        pool.close()?;
+    }
+
+    write_zip_comic_info(comic_path, info)?;
+
+    // 改写期间读取请求可能重新打开了旧文件，丢弃这些句柄
+    let reopened = cache.pools.lock()?.pop(comic_path);
+    if let Some(pool) = reopened {
+        pool.close()?;
+    }
+
+    open_comic(comic_path, cache)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// 两页的文件夹漫画（只读取字节，内容不必是有效图片）
+    fn folder_comic() -> (TestDir, String) {
+        let dir = TestDir::new("pool");
+        dir.write("001.png", b"page one");
+        dir.write("002.png", b"page two");
+        let path = dir.path().to_string_lossy().into_owned();
+        (dir, path)
+    }
+
+    fn total(pool: &SourcePool) -> usize {
+        pool.state.lock().unwrap().total
+    }
+
+    #[test]
+    fn pool_reuses_checked_in_handles() {
+        let (_dir, path) = folder_comic();
+        let pool = SourcePool::new(&path, None);
+
+        let page = pool.page(1).unwrap();
+        assert_eq!(page.name, "002.png");
+        assert_eq!(total(&pool), 1);
+
+        for _ in 0..3 {
+            let mut source = pool.checkout().unwrap();
+            assert_eq!(source.get().read_page(&page).unwrap(), b"page two");
+        }
+        assert_eq!(total(&pool), 1);
+        assert_eq!(pool.state.lock().unwrap().idle.len(), 1);
+    }
+
+    #[test]
+    fn pool_caps_open_handles_and_waits_for_checkin() {
+        let (_dir, path) = folder_comic();
+        let pool = &SourcePool::new(&path, None);
+
+        let mut held: Vec<_> = (0..MAX_HANDLES_PER_SOURCE).map(|_| pool.checkout().unwrap()).collect();
+        assert_eq!(total(pool), MAX_HANDLES_PER_SOURCE);
+
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                let source = pool.checkout();
+                tx.send(source.is_ok()).unwrap();
+            });
+
+            // 达到上限时等待归还，不会打开第 5 个句柄
+            assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+            held.pop();
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(true));
+        });
+        assert_eq!(total(pool), MAX_HANDLES_PER_SOURCE);
+    }
+
+    #[test]
+    fn closed_pool_waits_for_handles_and_rejects_checkouts() {
+        let (_dir, path) = folder_comic();
+        let pool = &SourcePool::new(&path, None);
+        let idle = pool.checkout().unwrap();
+        let in_use = pool.checkout().unwrap();
+        drop(idle);
+
+        let (tx, rx) = mpsc::channel();
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                tx.send(pool.close().is_ok()).unwrap();
+            });
+
+            // 空闲句柄立即释放，使用中的句柄归还后 close 才返回
+            assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+            drop(in_use);
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(true));
+        });
+
+        assert_eq!(total(pool), 0);
+        assert_eq!(
+            pool.checkout().err(),
+            Some(ComicError::ComicClosed { path: path.clone() })
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::AppHandle;
+
+use crate::comic_source::{get_page_info, read_page_entry, PageInfo, SourceCache};
+use crate::error::ComicError;
+use crate::image_handler::get_mime_type;
+use crate::library::{get_page_crop_cached, LibraryDb};
+use crate::page_cache::PageKey;
+
+/// 与背景色的亮度差不超过该值视为背景
+const BORDER_TOLERANCE: u8 = 24;
+
+/// 一行（列）中允许的非背景像素比例（扫描噪点、污渍）
+const BORDER_NOISE_RATIO: f32 = 0.005;
+
+/// 裁剪后至少保留原尺寸的比例，避免把大面积留白的页面裁没
+const MIN_KEEP_RATIO: f32 = 0.5;
+
+/// 裁剪后的 JPEG 质量
+const CROPPED_QUALITY: u8 = 90;
+
+/// 裁剪区域（像素）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropBox {
+    /// 整张图片（不裁剪）
+    pub fn full(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_full(&self, width: u32, height: u32) -> bool {
+        *self == Self::full(width, height)
+    }
+}
+
+/// 取四个角中出现最多的亮度作为背景色；四个角互不相同时认为没有统一边框
+fn background_luma(gray: &GrayImage) -> Option<u8> {
+    let (w, h) = gray.dimensions();
+    let corners = [
+        gray.get_pixel(0, 0)[0],
+        gray.get_pixel(w - 1, 0)[0],
+        gray.get_pixel(0, h - 1)[0],
+        gray.get_pixel(w - 1, h - 1)[0],
+    ];
+
+    corners
+        .iter()
+        .copied()
+        .find(|&c| corners.iter().filter(|&&o| o.abs_diff(c) <= BORDER_TOLERANCE).count() >= 3)
+}
+
+/// 一行（列）是否为背景：非背景像素不超过噪点阈值
+fn is_background_line(pixels: impl Iterator<Item = u8>, len: u32, background: u8) -> bool {
+    let limit = (len as f32 * BORDER_NOISE_RATIO) as usize;
+    pixels.filter(|p| p.abs_diff(background) > BORDER_TOLERANCE).count() <= limit
+}
+
+/// 检测图片四周的纯色边框（白边、黑边），返回去掉边框后的区域
+pub fn detect_crop_box(img: &DynamicImage) -> CropBox {
+    let gray = img.to_luma8();
+    let (w, h) = gray.dimensions();
+
+    if w < 3 || h < 3 {
+        return CropBox::full(w, h);
+    }
+    let Some(background) = background_luma(&gray) else {
+        return CropBox::full(w, h);
+    };
+
+    let row = |y: u32| is_background_line((0..w).map(|x| gray.get_pixel(x, y)[0]), w, background);
+    let column = |x: u32| is_background_line((0..h).map(|y| gray.get_pixel(x, y)[0]), h, background);
+
+    let top = (0..h).find(|&y| !row(y));
+    let Some(top) = top else {
+        // 整张图都是背景色
+        return CropBox::full(w, h);
+    };
+    let bottom = (top..h).rev().find(|&y| !row(y)).unwrap_or(top);
+    let left = (0..w).find(|&x| !column(x)).unwrap_or(0);
+    let right = (left..w).rev().find(|&x| !column(x)).unwrap_or(w - 1);
+
+    let crop = CropBox {
+        x: left,
+        y: top,
+        width: right - left + 1,
+        height: bottom - top + 1,
+    };
+
+    if (crop.width as f32) < w as f32 * MIN_KEEP_RATIO || (crop.height as f32) < h as f32 * MIN_KEEP_RATIO {
+        return CropBox::full(w, h);
+    }
+    crop
+}
+
+/// 检测图片数据的裁剪区域
+pub fn detect_crop_box_from_bytes(buffer: &[u8]) -> Result<CropBox, ComicError> {
+    let img = image::load_from_memory(buffer).map_err(ComicError::decode)?;
+    Ok(detect_crop_box(&img))
+}
+
+/// 按裁剪区域裁剪并重新编码（不透明图片为 JPEG，带透明通道为 PNG），返回数据和 MIME 类型；
+/// 区域为整张图片时返回 None，由调用方直接使用原图
+pub fn crop_image(buffer: &[u8], crop: CropBox) -> Result<Option<(Vec<u8>, &'static str)>, ComicError> {
+    let img = image::load_from_memory(buffer).map_err(ComicError::decode)?;
+    if crop.is_full(img.width(), img.height()) {
+        return Ok(None);
+    }
+
+    let cropped = img.crop_imm(crop.x, crop.y, crop.width, crop.height);
+    let mut output = Vec::new();
+
+    if cropped.color().has_alpha() {
+        cropped
+            .write_to(&mut std::io::Cursor::new(&mut output), image::ImageFormat::Png)
+            .map_err(ComicError::encode)?;
+        Ok(Some((output, "image/png")))
+    } else {
+        let rgb = cropped.to_rgb8();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, CROPPED_QUALITY)
+            .encode_image(&rgb)
+            .map_err(ComicError::encode)?;
+        Ok(Some((output, "image/jpeg")))
+    }
+}
+
+/// 读取去掉边框后的页面，返回页面信息、数据和 MIME 类型；裁剪结果缓存在页面缓存中
+pub fn read_page_cropped(
+    app: &AppHandle,
+    db: &LibraryDb,
+    sources: &SourceCache,
+    comic_path: &str,
+    page_index: usize,
+) -> Result<(PageInfo, Vec<u8>, &'static str), ComicError> {
+    let crop = get_page_crop_cached(app, db, sources, comic_path, page_index)?;
+    let page = get_page_info(comic_path, page_index, sources)?;
+    let key = PageKey::new(comic_path, &page.path)
+        .with_variant(format!("crop:{}:{}:{}:{}", crop.x, crop.y, crop.width, crop.height));
+
+    if let Some(buffer) = sources.pages.get(&key) {
+        let mime = if buffer.starts_with(b"\x89PNG") { "image/png" } else { "image/jpeg" };
+        return Ok((page, buffer.to_vec(), mime));
+    }
+
+    let (page, original) = read_page_entry(comic_path, page_index, sources)?;
+    match crop_image(&original, crop)? {
+        Some((buffer, mime)) => {
+            sources.pages.insert(key, Arc::new(buffer.clone()));
+            Ok((page, buffer, mime))
+        }
+        None => {
+            let mime = get_mime_type(&page.name);
+            Ok((page, original, mime))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// size x size 的白底页面，content 区域（x, y, 宽, 高）填充深灰
+    fn page(size: u32, content: (u32, u32, u32, u32)) -> GrayImage {
+        let (cx, cy, cw, ch) = content;
+        GrayImage::from_fn(size, size, |x, y| {
+            if (cx..cx + cw).contains(&x) && (cy..cy + ch).contains(&y) {
+                Luma([40])
+            } else {
+                Luma([255])
+            }
+        })
+    }
+
+    fn detect(gray: GrayImage) -> CropBox {
+        detect_crop_box(&DynamicImage::ImageLuma8(gray))
+    }
+
+    #[test]
+    fn crops_uniform_border() {
+        let crop = detect(page(400, (20, 30, 350, 340)));
+        assert_eq!(crop, CropBox { x: 20, y: 30, width: 350, height: 340 });
+    }
+
+    #[test]
+    fn border_shades_within_tolerance_are_background() {
+        let mut gray = page(400, (20, 20, 360, 360));
+        // 偏灰的扫描边框仍算背景，差值超出容差的像素算内容
+        for x in 0..400 {
+            gray.put_pixel(x, 5, Luma([255 - BORDER_TOLERANCE]));
+            gray.put_pixel(x, 10, Luma([255 - BORDER_TOLERANCE - 1]));
+        }
+        let crop = detect(gray);
+        assert_eq!(crop, CropBox { x: 20, y: 10, width: 360, height: 370 });
+    }
+
+    #[test]
+    fn sparse_noise_in_the_border_is_ignored() {
+        let mut gray = page(400, (20, 20, 360, 360));
+        // 400 像素的行最多容忍 2 个噪点
+        gray.put_pixel(100, 5, Luma([0]));
+        gray.put_pixel(200, 5, Luma([0]));
+        assert_eq!(detect(gray.clone()), CropBox { x: 20, y: 20, width: 360, height: 360 });
+
+        gray.put_pixel(300, 5, Luma([0]));
+        assert_eq!(detect(gray), CropBox { x: 20, y: 5, width: 360, height: 375 });
+    }
+
+    #[test]
+    fn keeps_full_page_when_crop_would_remove_too_much() {
+        let crop = detect(page(400, (150, 150, 100, 100)));
+        assert!(crop.is_full(400, 400));
+    }
+
+    #[test]
+    fn keeps_full_page_without_uniform_corners_or_content() {
+        assert!(detect(page(400, (0, 0, 400, 200))).is_full(400, 400));
+        assert!(detect(page(400, (0, 0, 0, 0))).is_full(400, 400));
+    }
+}
@@ -0,0 +1,272 @@
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::comic_source::{ComicMetadata, ComicSource, PageInfo};
+use crate::error::ComicError;
+use crate::i18n::{tr, Msg};
+use crate::file_system::{is_image_file, ArchiveKind};
+use crate::zip_handler::{open_zip_archive, read_zip_entry, read_zip_entry_dimensions};
+
+/// EPUB 容器描述文件，指向 OPF 包文件
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+
+/// XLink 命名空间（SVG 中的 xlink:href）
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// OPF 包文件解析结果
+struct OpfPackage {
+    title: Option<String>,
+    author: Option<String>,
+    right_to_left: bool,
+    /// 按 spine 顺序排列的条目（ZIP 内路径, media-type）
+    spine: Vec<(String, String)>,
+}
+
+/// 以 XML 方式解析文本，允许 XHTML 的 DOCTYPE 声明
+fn parse_xml(text: &str) -> Result<Document<'_>, roxmltree::Error> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(text, options)
+}
+
+/// 读取 ZIP 中的文本文件
+fn read_zip_text(archive: &mut ZipArchive<BufReader<File>>, epub_path: &str, name: &str) -> Result<String, ComicError> {
+    let buffer = read_zip_entry(archive, epub_path, name)?;
+    String::from_utf8(buffer).map_err(|e| ComicError::metadata(e).with_path(epub_path))
+}
+
+/// URL 百分号解码（EPUB 中的 href 为 URL 编码）
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let byte = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = byte {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// 将 href 解析为 ZIP 内的绝对路径（相对于引用它的文件）
+fn resolve_href(base_file: &str, href: &str) -> String {
+    let href = href.split(['#', '?']).next().unwrap_or_default();
+    let href = percent_decode(href);
+
+    let mut parts: Vec<&str> = match base_file.rfind('/') {
+        Some(pos) => base_file[..pos].split('/').collect(),
+        None => Vec::new(),
+    };
+
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+
+    parts.join("/")
+}
+
+/// 按本地名查找第一个子孙元素
+fn find_element<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.descendants()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// 从 container.xml 获取 OPF 文件路径
+fn parse_container(text: &str) -> Result<String, ComicError> {
+    let doc = parse_xml(text).map_err(ComicError::metadata)?;
+
+    find_element(doc.root(), "rootfile")
+        .and_then(|n| n.attribute("full-path"))
+        .map(|p| p.to_string())
+        .ok_or_else(|| ComicError::metadata(tr(Msg::MissingOpf, &[])))
+}
+
+/// 解析 OPF 包文件：元数据、清单和 spine 顺序
+fn parse_opf(text: &str, opf_path: &str) -> Result<OpfPackage, ComicError> {
+    let doc = parse_xml(text).map_err(ComicError::metadata)?;
+    let root = doc.root();
+
+    let metadata_text = |name: &str| {
+        find_element(root, "metadata")
+            .and_then(|metadata| find_element(metadata, name))
+            .and_then(|n| n.text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+
+    // 清单：id -> (路径, media-type)
+    let mut manifest: HashMap<&str, (String, String)> = HashMap::new();
+    if let Some(manifest_node) = find_element(root, "manifest") {
+        for item in manifest_node.children().filter(|n| n.tag_name().name() == "item") {
+            if let (Some(id), Some(href)) = (item.attribute("id"), item.attribute("href")) {
+                let media_type = item.attribute("media-type").unwrap_or_default().to_string();
+                manifest.insert(id, (resolve_href(opf_path, href), media_type));
+            }
+        }
+    }
+
+    let spine_node = find_element(root, "spine").ok_or_else(|| ComicError::metadata(tr(Msg::MissingSpine, &[])))?;
+    let right_to_left = spine_node.attribute("page-progression-direction") == Some("rtl");
+
+    let spine = spine_node
+        .children()
+        .filter(|n| n.tag_name().name() == "itemref")
+        .filter_map(|n| n.attribute("idref"))
+        .filter_map(|idref| manifest.get(idref).cloned())
+        .collect();
+
+    Ok(OpfPackage {
+        title: metadata_text("title"),
+        author: metadata_text("creator"),
+        right_to_left,
+        spine,
+    })
+}
+
+/// 查找 XHTML 页面引用的第一张图片（<img src> 或 SVG <image xlink:href>）
+fn find_page_image(text: &str, page_path: &str) -> Option<String> {
+    let doc = parse_xml(text).ok()?;
+
+    doc.descendants()
+        .filter(|n| n.is_element())
+        .find_map(|n| match n.tag_name().name() {
+            "img" => n.attribute("src"),
+            "image" => n.attribute((XLINK_NS, "href")).or_else(|| n.attribute("href")),
+            _ => None,
+        })
+        .map(|href| resolve_href(page_path, href))
+}
+
+/// EPUB 漫画来源：按 OPF spine 顺序读取固定版式页面，复用 ZIP 句柄
+pub struct EpubSource {
+    path: String,
+    archive: ZipArchive<BufReader<File>>,
+    pages: Vec<PageInfo>,
+    title: Option<String>,
+    author: Option<String>,
+    right_to_left: bool,
+}
+
+impl EpubSource {
+    /// 打开 EPUB 文件，解析 OPF 得到页面顺序和元数据
+    pub fn open(epub_path: &str) -> Result<Self, ComicError> {
+        let mut archive = open_zip_archive(epub_path)?;
+
+        let container = read_zip_text(&mut archive, epub_path, CONTAINER_PATH)?;
+        let opf_path = parse_container(&container).map_err(|e| e.with_path(epub_path))?;
+        let opf = read_zip_text(&mut archive, epub_path, &opf_path)?;
+        let package = parse_opf(&opf, &opf_path).map_err(|e| e.with_path(epub_path))?;
+
+        let mut pages: Vec<PageInfo> = Vec::new();
+
+        for (item_path, media_type) in &package.spine {
+            // spine 可以直接引用图片，也可以引用包含图片的 XHTML 页面
+            let image_path = if media_type.starts_with("image/") {
+                Some(item_path.clone())
+            } else {
+                read_zip_text(&mut archive, epub_path, item_path)
+                    .ok()
+                    .and_then(|xhtml| find_page_image(&xhtml, item_path))
+            };
+
+            let Some(image_path) = image_path else {
+                continue;
+            };
+
+            if !is_image_file(Path::new(&image_path)) || pages.iter().any(|p| p.path == image_path) {
+                continue;
+            }
+
+            let size = match archive.by_name(&image_path) {
+                Ok(file) => file.size(),
+                Err(_) => continue,
+            };
+
+            let name = Path::new(&image_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| image_path.clone());
+
+            pages.push(PageInfo {
+                index: pages.len(),
+                name,
+                path: image_path,
+                size,
+                page_type: None,
+                double_page: false,
+            });
+        }
+
+        Ok(Self {
+            path: epub_path.to_string(),
+            archive,
+            pages,
+            title: package.title,
+            author: package.author,
+            right_to_left: package.right_to_left,
+        })
+    }
+}
+
+impl ComicSource for EpubSource {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        Some(ArchiveKind::Epub)
+    }
+
+    fn pages(&self) -> &[PageInfo] {
+        &self.pages
+    }
+
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError> {
+        read_zip_entry(&mut self.archive, &self.path, &page.path)
+    }
+
+    fn page_dimensions(&mut self, page: &PageInfo) -> Result<(u32, u32), ComicError> {
+        read_zip_entry_dimensions(&mut self.archive, &self.path, &page.path)
+    }
+
+    fn metadata(&mut self) -> Result<ComicMetadata, ComicError> {
+        let name = Path::new(&self.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.clone());
+
+        Ok(ComicMetadata {
+            path: self.path.clone(),
+            name,
+            archive_kind: self.archive_kind(),
+            page_count: self.pages.len(),
+            title: self.title.clone(),
+            author: self.author.clone(),
+            right_to_left: self.right_to_left,
+            comic_info: None,
+        })
+    }
+}
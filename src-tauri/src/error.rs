@@ -0,0 +1,284 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+use std::sync::PoisonError;
+
+use crate::i18n::{tr, Msg};
+
+/// 后端统一的错误类型
+///
+/// 传给前端时序列化为 `{ kind, code, path, source, message }`：
+/// `kind` 为错误大类，`code` 为稳定的错误码（前端据此判断和本地化），
+/// `path` 为相关的文件路径，`source` 为底层库的原始错误信息，`message` 为按当前语言生成的描述
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComicError {
+    /// 文件或目录不存在
+    NotFound { path: String },
+    /// 不支持的漫画格式
+    UnsupportedFormat { path: String },
+    /// 文件读写失败
+    Io { path: Option<String>, source: String },
+    /// 压缩包损坏或无法解析
+    CorruptArchive { path: String, source: String },
+    /// 压缩包中找不到条目
+    EntryNotFound { path: String, entry: String },
+    /// 页面索引越界
+    PageOutOfRange { path: String, index: usize },
+    /// 分块索引越界
+    TileOutOfRange { path: String, index: usize },
+    /// 分块高度无效（为 0）
+    InvalidTileHeight { height: u32 },
+    /// 漫画中没有图片
+    EmptyComic { path: String },
+    /// 漫画已关闭（正在被改写）
+    ComicClosed { path: String },
+    /// 该格式不支持写入元数据
+    ReadOnlyFormat { path: String },
+    /// ComicInfo.xml / EPUB 描述文件无法解析
+    InvalidMetadata { path: Option<String>, source: String },
+    /// 图片无法识别或解码
+    ImageDecode { path: Option<String>, source: String },
+    /// 图片编码失败
+    ImageEncode { source: String },
+    /// PDF 加载或渲染失败
+    Pdf { path: Option<String>, source: String },
+    /// 漫画库数据库错误
+    Database { source: String },
+    /// 数据序列化或解析失败
+    Serialization { source: String },
+    /// 数据文件来自更新版本的程序，无法安全读取
+    DataVersionTooNew { path: String, version: u32, supported: u32 },
+    /// 目录监听失败
+    Watcher { source: String },
+    /// 后台任务失败
+    Task { source: String },
+    /// 锁被破坏（持有锁的线程崩溃）
+    LockPoisoned,
+}
+
+impl ComicError {
+    pub fn not_found(path: impl AsRef<Path>) -> Self {
+        ComicError::NotFound {
+            path: path_string(path),
+        }
+    }
+
+    pub fn io(path: impl AsRef<Path>, source: impl fmt::Display) -> Self {
+        ComicError::Io {
+            path: Some(path_string(path)),
+            source: source.to_string(),
+        }
+    }
+
+    pub fn corrupt(path: impl AsRef<Path>, source: impl fmt::Display) -> Self {
+        ComicError::CorruptArchive {
+            path: path_string(path),
+            source: source.to_string(),
+        }
+    }
+
+    pub fn entry_not_found(path: impl AsRef<Path>, entry: &str) -> Self {
+        ComicError::EntryNotFound {
+            path: path_string(path),
+            entry: entry.to_string(),
+        }
+    }
+
+    pub fn decode(source: impl fmt::Display) -> Self {
+        ComicError::ImageDecode {
+            path: None,
+            source: source.to_string(),
+        }
+    }
+
+    pub fn encode(source: impl fmt::Display) -> Self {
+        ComicError::ImageEncode {
+            source: source.to_string(),
+        }
+    }
+
+    pub fn metadata(source: impl fmt::Display) -> Self {
+        ComicError::InvalidMetadata {
+            path: None,
+            source: source.to_string(),
+        }
+    }
+
+    pub fn pdf(path: impl AsRef<Path>, source: impl fmt::Display) -> Self {
+        ComicError::Pdf {
+            path: Some(path_string(path)),
+            source: source.to_string(),
+        }
+    }
+
+    /// 补充相关的文件路径（底层函数不知道路径时由调用方补上）
+    pub fn with_path(mut self, file: impl AsRef<Path>) -> Self {
+        match &mut self {
+            ComicError::Io { path, .. }
+            | ComicError::InvalidMetadata { path, .. }
+            | ComicError::ImageDecode { path, .. }
+            | ComicError::Pdf { path, .. }
+                if path.is_none() =>
+            {
+                *path = Some(path_string(file));
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// 错误大类
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ComicError::NotFound { .. } | ComicError::UnsupportedFormat { .. } | ComicError::Io { .. } => "file",
+            ComicError::CorruptArchive { .. } | ComicError::EntryNotFound { .. } => "archive",
+            ComicError::PageOutOfRange { .. }
+            | ComicError::TileOutOfRange { .. }
+            | ComicError::InvalidTileHeight { .. }
+            | ComicError::EmptyComic { .. }
+            | ComicError::ComicClosed { .. }
+            | ComicError::ReadOnlyFormat { .. }
+            | ComicError::InvalidMetadata { .. } => "comic",
+            ComicError::ImageDecode { .. } | ComicError::ImageEncode { .. } => "image",
+            ComicError::Pdf { .. } => "pdf",
+            ComicError::Database { .. } | ComicError::Serialization { .. } | ComicError::DataVersionTooNew { .. } => {
+                "storage"
+            }
+            ComicError::Watcher { .. } | ComicError::Task { .. } | ComicError::LockPoisoned => "internal",
+        }
+    }
+
+    /// 稳定的错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            ComicError::NotFound { .. } => "not_found",
+            ComicError::UnsupportedFormat { .. } => "unsupported_format",
+            ComicError::Io { .. } => "io_error",
+            ComicError::CorruptArchive { .. } => "corrupt_archive",
+            ComicError::EntryNotFound { .. } => "entry_not_found",
+            ComicError::PageOutOfRange { .. } => "page_out_of_range",
+            ComicError::TileOutOfRange { .. } => "tile_out_of_range",
+            ComicError::InvalidTileHeight { .. } => "invalid_tile_height",
+            ComicError::EmptyComic { .. } => "empty_comic",
+            ComicError::ComicClosed { .. } => "comic_closed",
+            ComicError::ReadOnlyFormat { .. } => "read_only_format",
+            ComicError::InvalidMetadata { .. } => "invalid_metadata",
+            ComicError::ImageDecode { .. } => "image_decode",
+            ComicError::ImageEncode { .. } => "image_encode",
+            ComicError::Pdf { .. } => "pdf_error",
+            ComicError::Database { .. } => "database_error",
+            ComicError::Serialization { .. } => "serialization_error",
+            ComicError::DataVersionTooNew { .. } => "data_version_too_new",
+            ComicError::Watcher { .. } => "watcher_error",
+            ComicError::Task { .. } => "task_failed",
+            ComicError::LockPoisoned => "lock_poisoned",
+        }
+    }
+
+    /// 相关的文件路径
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            ComicError::NotFound { path }
+            | ComicError::UnsupportedFormat { path }
+            | ComicError::CorruptArchive { path, .. }
+            | ComicError::EntryNotFound { path, .. }
+            | ComicError::PageOutOfRange { path, .. }
+            | ComicError::TileOutOfRange { path, .. }
+            | ComicError::EmptyComic { path }
+            | ComicError::ComicClosed { path }
+            | ComicError::ReadOnlyFormat { path }
+            | ComicError::DataVersionTooNew { path, .. } => Some(path),
+            ComicError::Io { path, .. }
+            | ComicError::InvalidMetadata { path, .. }
+            | ComicError::ImageDecode { path, .. }
+            | ComicError::Pdf { path, .. } => path.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// 底层库的原始错误信息
+    pub fn source_message(&self) -> Option<&str> {
+        match self {
+            ComicError::Io { source, .. }
+            | ComicError::CorruptArchive { source, .. }
+            | ComicError::InvalidMetadata { source, .. }
+            | ComicError::ImageDecode { source, .. }
+            | ComicError::ImageEncode { source }
+            | ComicError::Pdf { source, .. }
+            | ComicError::Database { source }
+            | ComicError::Serialization { source }
+            | ComicError::Watcher { source }
+            | ComicError::Task { source } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+fn path_string(path: impl AsRef<Path>) -> String {
+    path.as_ref().to_string_lossy().into_owned()
+}
+
+impl fmt::Display for ComicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ComicError::NotFound { path } => tr(Msg::NotFound, &[path]),
+            ComicError::UnsupportedFormat { path } => tr(Msg::UnsupportedFormat, &[path]),
+            ComicError::Io { path: Some(path), source } => tr(Msg::IoWithPath, &[path, source]),
+            ComicError::Io { path: None, source } => tr(Msg::Io, &[source]),
+            ComicError::CorruptArchive { path, source } => tr(Msg::CorruptArchive, &[path, source]),
+            ComicError::EntryNotFound { entry, .. } => tr(Msg::EntryNotFound, &[entry]),
+            ComicError::PageOutOfRange { index, .. } => tr(Msg::PageOutOfRange, &[index]),
+            ComicError::TileOutOfRange { index, .. } => tr(Msg::TileOutOfRange, &[index]),
+            ComicError::InvalidTileHeight { height } => tr(Msg::InvalidTileHeight, &[height]),
+            ComicError::EmptyComic { path } => tr(Msg::EmptyComic, &[path]),
+            ComicError::ComicClosed { path } => tr(Msg::ComicClosed, &[path]),
+            ComicError::ReadOnlyFormat { path } => tr(Msg::ReadOnlyFormat, &[path]),
+            ComicError::InvalidMetadata { source, .. } => tr(Msg::InvalidMetadata, &[source]),
+            ComicError::ImageDecode { source, .. } => tr(Msg::ImageDecode, &[source]),
+            ComicError::ImageEncode { source } => tr(Msg::ImageEncode, &[source]),
+            ComicError::Pdf { source, .. } => tr(Msg::Pdf, &[source]),
+            ComicError::Database { source } => tr(Msg::Database, &[source]),
+            ComicError::Serialization { source } => tr(Msg::Serialization, &[source]),
+            ComicError::DataVersionTooNew { path, version, supported } => {
+                tr(Msg::DataVersionTooNew, &[path, version, supported])
+            }
+            ComicError::Watcher { source } => tr(Msg::Watcher, &[source]),
+            ComicError::Task { source } => tr(Msg::Task, &[source]),
+            ComicError::LockPoisoned => tr(Msg::LockPoisoned, &[]),
+        };
+        f.write_str(&message)
+    }
+}
+
+impl std::error::Error for ComicError {}
+
+impl Serialize for ComicError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ComicError", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("path", &self.path())?;
+        state.serialize_field("source", &self.source_message())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl<T> From<PoisonError<T>> for ComicError {
+    fn from(_: PoisonError<T>) -> Self {
+        ComicError::LockPoisoned
+    }
+}
+
+impl From<rusqlite::Error> for ComicError {
+    fn from(e: rusqlite::Error) -> Self {
+        ComicError::Database { source: e.to_string() }
+    }
+}
+
+impl From<serde_json::Error> for ComicError {
+    fn from(e: serde_json::Error) -> Self {
+        ComicError::Serialization { source: e.to_string() }
+    }
+}
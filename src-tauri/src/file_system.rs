@@ -1,11 +1,58 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
+use std::time::UNIX_EPOCH;
 use walkdir::WalkDir;
 
+use crate::comic_info::{apply_comic_info, is_comic_info_file, parse_comic_info, ComicInfo};
+use crate::comic_source::{build_page_list, open_source, ComicSource, PageInfo};
+use crate::error::ComicError;
+use crate::i18n::{tr, Msg};
+use crate::image_handler::{get_image_dimensions, read_image_as_bytes};
+use crate::library::LibraryScan;
+use crate::pdf_handler::get_pdf_page_count;
+
 /// 支持的图片格式
 const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp", "tiff", "tif"];
 
+/// 压缩包（及 PDF 等文档）类型，前端据此选择对应的读取后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveKind {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "rar")]
+    Rar,
+    #[serde(rename = "7z")]
+    SevenZip,
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "pdf")]
+    Pdf,
+    #[serde(rename = "epub")]
+    Epub,
+}
+
+impl ArchiveKind {
+    /// 根据扩展名判断压缩包类型
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if is_zip_file(path) {
+            Some(Self::Zip)
+        } else if is_rar_file(path) {
+            Some(Self::Rar)
+        } else if is_7z_file(path) {
+            Some(Self::SevenZip)
+        } else if is_tar_file(path) {
+            Some(Self::Tar)
+        } else if is_pdf_file(path) {
+            Some(Self::Pdf)
+        } else if is_epub_file(path) {
+            Some(Self::Epub)
+        } else {
+            None
+        }
+    }
+}
+
 /// 文件节点结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileNode {
@@ -15,8 +62,8 @@ pub struct FileNode {
     pub is_directory: bool,
     #[serde(rename = "isComic")]
     pub is_comic: bool,
-    #[serde(rename = "isZip")]
-    pub is_zip: bool,
+    #[serde(rename = "archiveKind")]
+    pub archive_kind: Option<ArchiveKind>,
     #[serde(rename = "imageCount")]
     pub image_count: u32,
     pub children: Option<Vec<FileNode>>,
@@ -40,6 +87,64 @@ pub fn is_zip_file(path: &Path) -> bool {
     false
 }
 
+/// 检查文件是否为 RAR
+pub fn is_rar_file(path: &Path) -> bool {
+    if let Some(ext) = path.extension() {
+        let ext_lower = ext.to_string_lossy().to_lowercase();
+        return ext_lower == "rar" || ext_lower == "cbr";
+    }
+    false
+}
+
+/// 检查文件是否为 7z
+pub fn is_7z_file(path: &Path) -> bool {
+    if let Some(ext) = path.extension() {
+        let ext_lower = ext.to_string_lossy().to_lowercase();
+        return ext_lower == "7z" || ext_lower == "cb7";
+    }
+    false
+}
+
+/// 检查文件是否为 TAR（包括 gzip/xz 压缩的 TAR）
+pub fn is_tar_file(path: &Path) -> bool {
+    if let Some(name) = path.file_name() {
+        let name_lower = name.to_string_lossy().to_lowercase();
+        return [".tar", ".cbt", ".tgz", ".txz", ".tar.gz", ".tar.xz"]
+            .iter()
+            .any(|ext| name_lower.ends_with(ext));
+    }
+    false
+}
+
+/// 检查文件是否为 PDF
+pub fn is_pdf_file(path: &Path) -> bool {
+    if let Some(ext) = path.extension() {
+        return ext.to_string_lossy().to_lowercase() == "pdf";
+    }
+    false
+}
+
+/// 检查文件是否为 EPUB
+pub fn is_epub_file(path: &Path) -> bool {
+    if let Some(ext) = path.extension() {
+        return ext.to_string_lossy().to_lowercase() == "epub";
+    }
+    false
+}
+
+/// 获取漫画文件的页数：PDF 直接读取页数，压缩包打开后统计图片
+/// 开销较大，扫描时只对新增或变化的文件调用
+fn comic_file_page_count(path: &Path, archive_kind: Option<ArchiveKind>) -> u32 {
+    let path_str = path.to_string_lossy();
+    match archive_kind {
+        Some(ArchiveKind::Pdf) => get_pdf_page_count(&path_str).unwrap_or(0),
+        Some(_) => open_source(&path_str)
+            .map(|source| source.pages().len() as u32)
+            .unwrap_or(0),
+        None => 0,
+    }
+}
+
 /// 使用自然排序对文件节点排序
 fn sort_nodes(nodes: &mut [FileNode]) {
     nodes.sort_by(|a, b| {
@@ -67,11 +172,12 @@ pub fn directory_has_images(path: &Path) -> (bool, u32) {
 }
 
 /// 扫描目录并构建文件树
-pub fn scan_directory(path: &str, depth: u32, max_depth: u32) -> Result<FileNode, String> {
+/// 页数通过 LibraryScan 获取，未变化的条目不会重新统计
+pub fn scan_directory(path: &str, depth: u32, max_depth: u32, scan: &mut LibraryScan) -> Result<FileNode, ComicError> {
     let path = Path::new(path);
     
     if !path.exists() {
-        return Err(format!("路径不存在: {}", path.display()));
+        return Err(ComicError::not_found(path));
     }
 
     let name = path
@@ -81,23 +187,23 @@ pub fn scan_directory(path: &str, depth: u32, max_depth: u32) -> Result<FileNode
 
     // 如果是文件
     if path.is_file() {
-        let is_zip = is_zip_file(path);
+        let archive_kind = ArchiveKind::from_path(path);
         return Ok(FileNode {
             name,
             path: path.to_string_lossy().to_string(),
             is_directory: false,
-            is_comic: is_zip,
-            is_zip,
-            image_count: 0,
+            is_comic: archive_kind.is_some(),
+            archive_kind,
+            image_count: scan.page_count(path, archive_kind, || comic_file_page_count(path, archive_kind)),
             children: None,
         });
     }
 
     // 如果是目录
-    let (has_direct_images, direct_image_count) = directory_has_images(path);
-    
+    let direct_image_count = scan.page_count(path, None, || directory_has_images(path).1);
+
     // 判断是否为漫画目录（直接包含图片）
-    let is_comic = has_direct_images;
+    let is_comic = direct_image_count > 0;
 
     // 如果达到最大深度，不再递归
     if depth >= max_depth {
@@ -106,7 +212,7 @@ pub fn scan_directory(path: &str, depth: u32, max_depth: u32) -> Result<FileNode
             path: path.to_string_lossy().to_string(),
             is_directory: true,
             is_comic,
-            is_zip: false,
+            archive_kind: None,
             image_count: direct_image_count,
             children: None,
         });
@@ -131,19 +237,22 @@ pub fn scan_directory(path: &str, depth: u32, max_depth: u32) -> Result<FileNode
                     &entry_path.to_string_lossy(),
                     depth + 1,
                     max_depth,
+                    scan,
                 ) {
                     children.push(child_node);
                 }
             } else if entry_path.is_file() {
-                // 只添加 ZIP 文件，不添加单独的图片文件
-                if is_zip_file(&entry_path) {
+                // 只添加压缩包文件，不添加单独的图片文件
+                if let Some(archive_kind) = ArchiveKind::from_path(&entry_path) {
                     children.push(FileNode {
                         name: entry_name,
                         path: entry_path.to_string_lossy().to_string(),
                         is_directory: false,
                         is_comic: true,
-                        is_zip: true,
-                        image_count: 0,
+                        archive_kind: Some(archive_kind),
+                        image_count: scan.page_count(&entry_path, Some(archive_kind), || {
+                            comic_file_page_count(&entry_path, Some(archive_kind))
+                        }),
                         children: None,
                     });
                 }
@@ -159,7 +268,7 @@ pub fn scan_directory(path: &str, depth: u32, max_depth: u32) -> Result<FileNode
         path: path.to_string_lossy().to_string(),
         is_directory: true,
         is_comic,
-        is_zip: false,
+        archive_kind: None,
         image_count: direct_image_count,
         children: if children.is_empty() {
             None
@@ -169,31 +278,144 @@ pub fn scan_directory(path: &str, depth: u32, max_depth: u32) -> Result<FileNode
     })
 }
 
-/// 获取目录中的图片列表
-pub fn get_images_in_directory(path: &str) -> Result<Vec<String>, String> {
-    let path = Path::new(path);
-    
-    if !path.exists() || !path.is_dir() {
-        return Err(format!("目录不存在: {}", path.display()));
+/// 文件的大小和修改时间（自 UNIX 纪元的纳秒数），用于判断缓存是否过期
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime: u128,
+}
+
+impl FileStamp {
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        let mtime = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        Self {
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            mtime,
+        }
     }
 
-    let mut images: Vec<String> = Vec::new();
-    
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let entry_path = entry.path();
-            if entry_path.is_file() && is_image_file(&entry_path) {
-                images.push(entry_path.to_string_lossy().to_string());
+    /// 修改时间（毫秒），与漫画库中记录的单位一致
+    pub fn mtime_millis(&self) -> i64 {
+        (self.mtime / 1_000_000) as i64
+    }
+}
+
+/// 漫画的变化标记
+/// 压缩包、PDF 等取文件本身；原地替换图片不会改变目录的修改时间，
+/// 因此文件夹漫画汇总目录本身（反映增删）和其中各文件的大小与最新修改时间
+pub fn comic_stamp(comic_path: &str) -> Option<FileStamp> {
+    let metadata = fs::metadata(comic_path).ok()?;
+    let mut stamp = FileStamp::from_metadata(&metadata);
+    if !metadata.is_dir() {
+        return Some(stamp);
+    }
+
+    for entry in fs::read_dir(comic_path).ok()?.flatten() {
+        let Ok(metadata) = entry.metadata() else { continue };
+        if metadata.is_file() {
+            let file = FileStamp::from_metadata(&metadata);
+            stamp.size += file.size;
+            stamp.mtime = stamp.mtime.max(file.mtime);
+        }
+    }
+    Some(stamp)
+}
+
+/// 页面的变化标记：文件夹漫画取页面图片文件本身，其他格式取漫画文件
+pub fn page_stamp(comic_path: &str, entry: &str) -> Option<FileStamp> {
+    let metadata = fs::metadata(comic_path).ok()?;
+    if metadata.is_dir() {
+        return fs::metadata(entry).ok().map(|m| FileStamp::from_metadata(&m));
+    }
+    Some(FileStamp::from_metadata(&metadata))
+}
+
+/// 文件夹漫画来源：直接包含图片的目录
+pub struct DirectorySource {
+    path: String,
+    pages: Vec<PageInfo>,
+    comic_info: Option<ComicInfo>,
+}
+
+impl DirectorySource {
+    /// 读取目录中的图片列表
+    pub fn open(dir_path: &str) -> Result<Self, ComicError> {
+        let path = Path::new(dir_path);
+
+        if !path.exists() || !path.is_dir() {
+            return Err(ComicError::not_found(path));
+        }
+
+        let mut entries = Vec::new();
+        let mut comic_info_path = None;
+
+        if let Ok(dir_entries) = fs::read_dir(path) {
+            for entry in dir_entries.flatten() {
+                let entry_path = entry.path();
+                if entry_path.is_file() {
+                    if is_comic_info_file(&entry_path) {
+                        comic_info_path = Some(entry_path.clone());
+                    }
+                    let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    entries.push((entry_path.to_string_lossy().to_string(), size));
+                }
             }
         }
+
+        let comic_info = comic_info_path.and_then(|info_path| {
+            let parsed = fs::read_to_string(&info_path)
+                .map_err(|e| ComicError::io(&info_path, e))
+                .and_then(|text| parse_comic_info(&text));
+            match parsed {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    eprintln!("{}", tr(Msg::WarnReadFailed, &[&info_path.display(), &e]));
+                    None
+                }
+            }
+        });
+
+        let mut pages = build_page_list(entries);
+        if let Some(info) = &comic_info {
+            pages = apply_comic_info(pages, info);
+        }
+
+        Ok(Self {
+            path: dir_path.to_string(),
+            pages,
+            comic_info,
+        })
+    }
+}
+
+impl ComicSource for DirectorySource {
+    fn path(&self) -> &str {
+        &self.path
     }
 
-    // 自然排序
-    images.sort_by(|a, b| {
-        let name_a = Path::new(a).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
-        let name_b = Path::new(b).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
-        natord::compare(&name_a, &name_b)
-    });
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        None
+    }
+
+    fn pages(&self) -> &[PageInfo] {
+        &self.pages
+    }
 
-    Ok(images)
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError> {
+        read_image_as_bytes(&page.path)
+    }
+
+    fn page_dimensions(&mut self, page: &PageInfo) -> Result<(u32, u32), ComicError> {
+        // 直接从文件头读取尺寸，无需读取整个文件
+        get_image_dimensions(&page.path)
+    }
+
+    fn comic_info(&self) -> Option<&ComicInfo> {
+        self.comic_info.as_ref()
+    }
 }
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+/// 后端消息使用的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Zh,
+    En,
+}
+
+impl Language {
+    /// 按系统区域设置选择语言：中文环境使用中文，其他使用英文；只在首次调用时检测
+    pub fn system() -> Self {
+        static SYSTEM: OnceLock<Language> = OnceLock::new();
+        *SYSTEM.get_or_init(|| match sys_locale::get_locale() {
+            Some(locale) if locale.to_lowercase().starts_with("zh") => Language::Zh,
+            _ => Language::En,
+        })
+    }
+}
+
+/// 当前语言；错误的 Display 和日志在任意线程中都会用到，因此放在全局
+static CURRENT: AtomicU8 = AtomicU8::new(LANGUAGE_UNSET);
+
+const LANGUAGE_UNSET: u8 = u8::MAX;
+
+/// 切换后端消息的语言（加载和保存设置时调用），None 为跟随系统
+pub fn set_language(language: Option<Language>) {
+    CURRENT.store(language.map_or(LANGUAGE_UNSET, |l| l as u8), Ordering::Relaxed);
+}
+
+/// 当前语言，未设置时跟随系统
+pub fn language() -> Language {
+    match CURRENT.load(Ordering::Relaxed) {
+        v if v == Language::Zh as u8 => Language::Zh,
+        v if v == Language::En as u8 => Language::En,
+        _ => Language::system(),
+    }
+}
+
+/// 消息目录：后端产生的所有用户可见文本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    // 错误
+    NotFound,
+    UnsupportedFormat,
+    IoWithPath,
+    Io,
+    CorruptArchive,
+    EntryNotFound,
+    PageOutOfRange,
+    TileOutOfRange,
+    InvalidTileHeight,
+    EmptyComic,
+    ComicClosed,
+    ReadOnlyFormat,
+    InvalidMetadata,
+    ImageDecode,
+    ImageEncode,
+    Pdf,
+    Database,
+    Serialization,
+    DataVersionTooNew,
+    Watcher,
+    Task,
+    LockPoisoned,
+    // 错误详情
+    MissingComicInfoRoot,
+    MissingOpf,
+    MissingSpine,
+    SplitPageFailed,
+    InvalidPageUrl,
+    PathNotAllowed,
+    NoResourceDir,
+    NoAppDataDir,
+    // 警告
+    Warning,
+    WarnReadFailed,
+    WarnDataMigrated,
+    WarnRenameImportedData,
+    WarnWatchFailed,
+    WarnScanFailed,
+    WarnLibraryUpdateFailed,
+    WarnEmitFailed,
+    WarnWatcherError,
+}
+
+impl Msg {
+    /// 各语言的消息模板，`{}` 按顺序替换为参数
+    fn template(self, language: Language) -> &'static str {
+        let (zh, en) = match self {
+            Msg::NotFound => ("路径不存在: {}", "Path not found: {}"),
+            Msg::UnsupportedFormat => ("不支持的漫画格式: {}", "Unsupported comic format: {}"),
+            Msg::IoWithPath => ("无法读写文件 {}: {}", "Cannot read or write {}: {}"),
+            Msg::Io => ("无法读写文件: {}", "Cannot read or write file: {}"),
+            Msg::CorruptArchive => ("无法读取压缩包 {}: {}", "Cannot read archive {}: {}"),
+            Msg::EntryNotFound => ("无法找到图片: {}", "Image not found: {}"),
+            Msg::PageOutOfRange => ("页面索引越界: {}", "Page index out of range: {}"),
+            Msg::TileOutOfRange => ("分块索引越界: {}", "Tile index out of range: {}"),
+            Msg::InvalidTileHeight => ("分块高度无效: {}", "Invalid tile height: {}"),
+            Msg::EmptyComic => ("漫画中没有图片: {}", "No images in comic: {}"),
+            Msg::ComicClosed => ("漫画已关闭: {}", "Comic is closed: {}"),
+            Msg::ReadOnlyFormat => (
+                "仅支持写入 ZIP/CBZ 的 ComicInfo: {}",
+                "ComicInfo can only be written to ZIP/CBZ: {}",
+            ),
+            Msg::InvalidMetadata => ("无法解析漫画元数据: {}", "Cannot parse comic metadata: {}"),
+            Msg::ImageDecode => ("无法解码图片: {}", "Cannot decode image: {}"),
+            Msg::ImageEncode => ("无法编码图片: {}", "Cannot encode image: {}"),
+            Msg::Pdf => ("无法处理 PDF: {}", "Cannot process PDF: {}"),
+            Msg::Database => ("漫画库错误: {}", "Library database error: {}"),
+            Msg::Serialization => ("无法解析数据: {}", "Cannot parse data: {}"),
+            Msg::DataVersionTooNew => (
+                "数据文件 {} 的版本为 {}，高于本程序支持的版本 {}，请升级程序",
+                "Data file {} is version {}, newer than the supported version {}; please update the app",
+            ),
+            Msg::Watcher => ("无法创建目录监听: {}", "Cannot watch directories: {}"),
+            Msg::Task => ("任务执行失败: {}", "Background task failed: {}"),
+            Msg::LockPoisoned => ("锁获取失败", "Failed to acquire lock"),
+            Msg::MissingComicInfoRoot => ("缺少 ComicInfo 根元素", "Missing ComicInfo root element"),
+            Msg::MissingOpf => ("EPUB 容器中缺少 OPF 文件", "EPUB container has no OPF file"),
+            Msg::MissingSpine => ("OPF 中缺少 spine", "OPF has no spine"),
+            Msg::SplitPageFailed => ("无法拆分页面: {}", "Cannot split page: {}"),
+            Msg::InvalidPageUrl => ("无效的页面地址: {}", "Invalid page URL: {}"),
+            Msg::PathNotAllowed => ("不允许访问的路径: {}", "Access to path not allowed: {}"),
+            Msg::NoResourceDir => ("无法获取程序目录: {}", "Cannot locate program directory: {}"),
+            Msg::NoAppDataDir => ("无法获取旧版应用数据目录: {}", "Cannot locate legacy app data directory: {}"),
+            Msg::Warning => ("警告：{}", "Warning: {}"),
+            Msg::WarnReadFailed => ("警告：无法读取 {}: {}", "Warning: cannot read {}: {}"),
+            Msg::WarnDataMigrated => (
+                "警告：数据文件已从版本 {} 升级到 {}，原文件备份为 {}",
+                "Warning: data file upgraded from version {} to {}, original backed up to {}",
+            ),
+            Msg::WarnRenameImportedData => (
+                "警告：数据已导入数据库，但无法重命名原文件 {}: {}",
+                "Warning: data was imported into the database but {} could not be renamed: {}",
+            ),
+            Msg::WarnWatchFailed => ("警告：无法监听目录 {}: {}", "Warning: cannot watch directory {}: {}"),
+            Msg::WarnScanFailed => ("警告：无法扫描 {}: {}", "Warning: cannot scan {}: {}"),
+            Msg::WarnLibraryUpdateFailed => ("警告：无法更新漫画库 {}: {}", "Warning: cannot update library for {}: {}"),
+            Msg::WarnEmitFailed => ("警告：无法推送 {}: {}", "Warning: cannot emit {}: {}"),
+            Msg::WarnWatcherError => ("警告：目录监听出错: {}", "Warning: directory watcher error: {}"),
+        };
+        match language {
+            Language::Zh => zh,
+            Language::En => en,
+        }
+    }
+}
+
+/// 按当前语言取出消息，并依次填入参数
+pub fn tr(msg: Msg, args: &[&dyn Display]) -> String {
+    let template = msg.template(language());
+    let mut output = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+
+    if let Some(first) = parts.next() {
+        output.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            output.push_str(&arg.to_string());
+        }
+        output.push_str(part);
+    }
+    output
+}
@@ -1,7 +1,13 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::Read;
 use std::path::Path;
 
+use crate::error::ComicError;
+
+/// 解析图片尺寸时依次尝试读取的字节数
+const HEADER_SNIFF_SIZES: [usize; 2] = [16 * 1024, 256 * 1024];
+
 /// 图片信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageInfo {
@@ -13,20 +19,132 @@ pub struct ImageInfo {
 }
 
 /// 获取图片尺寸
-pub fn get_image_dimensions(path: &str) -> Result<(u32, u32), String> {
+pub fn get_image_dimensions(path: &str) -> Result<(u32, u32), ComicError> {
     image::image_dimensions(path)
-        .map_err(|e| format!("无法获取图片尺寸: {}", e))
+        .map_err(|e| ComicError::decode(e).with_path(path))
+}
+
+/// 从内存中的图片数据获取尺寸（用于压缩包内的图片）
+pub fn get_image_dimensions_from_bytes(buffer: Vec<u8>) -> Result<(u32, u32), ComicError> {
+    let reader = image::ImageReader::new(std::io::Cursor::new(buffer))
+        .with_guessed_format()
+        .map_err(ComicError::decode)?;
+
+    reader.into_dimensions()
+        .map_err(ComicError::decode)
+}
+
+/// 读取数据流失败（路径由调用方补充）
+fn read_error(e: std::io::Error) -> ComicError {
+    ComicError::Io {
+        path: None,
+        source: e.to_string(),
+    }
+}
+
+/// 生成缩略图：按比例缩小到 max_size 以内（不放大），编码为 JPEG
+pub fn make_thumbnail(buffer: &[u8], max_size: u32, quality: u8) -> Result<Vec<u8>, ComicError> {
+    let img = image::load_from_memory(buffer).map_err(ComicError::decode)?;
+
+    let img = if img.width() > max_size || img.height() > max_size {
+        img.resize(max_size, max_size, image::imageops::FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    // JPEG 不支持透明通道，先转换为 RGB
+    let rgb = img.to_rgb8();
+    let mut output = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, quality)
+        .encode_image(&rgb)
+        .map_err(ComicError::encode)?;
+
+    Ok(output)
+}
+
+/// 缩放滤镜
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScaleFilter {
+    /// 质量最好，速度最慢
+    #[default]
+    Lanczos3,
+    CatmullRom,
+    /// 速度最快，适合低配设备
+    Triangle,
+}
+
+impl ScaleFilter {
+    fn filter_type(self) -> image::imageops::FilterType {
+        match self {
+            ScaleFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+            ScaleFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ScaleFilter::Triangle => image::imageops::FilterType::Triangle,
+        }
+    }
+}
+
+/// 按目标宽度等比缩小图片并重新编码（不透明图片为 JPEG，带透明通道为 PNG）；
+/// 不需要缩小时返回 None，由调用方直接使用原图
+pub fn scale_image(buffer: &[u8], target_width: u32, filter: ScaleFilter, quality: u8) -> Result<Option<Vec<u8>>, ComicError> {
+    let (width, height) = get_image_dimensions_from_bytes(buffer.to_vec())?;
+    if target_width == 0 || width <= target_width {
+        return Ok(None);
+    }
+
+    let img = image::load_from_memory(buffer).map_err(ComicError::decode)?;
+    let target_height = ((height as u64 * target_width as u64) / width as u64).max(1) as u32;
+    let img = img.resize_exact(target_width, target_height, filter.filter_type());
+
+    let mut output = Vec::new();
+    if img.color().has_alpha() {
+        img.write_to(&mut std::io::Cursor::new(&mut output), image::ImageFormat::Png)
+            .map_err(ComicError::encode)?;
+    } else {
+        let rgb = img.to_rgb8();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut output, quality)
+            .encode_image(&rgb)
+            .map_err(ComicError::encode)?;
+    }
+
+    Ok(Some(output))
+}
+
+/// 只读取图片开头部分解析尺寸：先读 16KB，不够再读到 256KB（JPEG 的 EXIF 可能较大），
+/// 仍失败时才读取剩余全部数据
+pub fn get_image_dimensions_from_reader(reader: &mut dyn Read) -> Result<(u32, u32), ComicError> {
+    let mut buffer = Vec::new();
+
+    for limit in HEADER_SNIFF_SIZES {
+        let wanted = (limit - buffer.len()) as u64;
+        let read = reader
+            .take(wanted)
+            .read_to_end(&mut buffer)
+            .map_err(read_error)?;
+
+        if let Ok(dimensions) = get_image_dimensions_from_bytes(buffer.clone()) {
+            return Ok(dimensions);
+        }
+        // 已读到末尾，数据完整但仍无法解析
+        if (read as u64) < wanted {
+            return get_image_dimensions_from_bytes(buffer);
+        }
+    }
+
+    reader
+        .read_to_end(&mut buffer)
+        .map_err(read_error)?;
+    get_image_dimensions_from_bytes(buffer)
 }
 
 /// 读取图片文件并返回 Base64 Data URL
-pub fn read_image_as_base64(path: &str) -> Result<String, String> {
+pub fn read_image_as_base64(path: &str) -> Result<String, ComicError> {
     let path_obj = Path::new(path);
     
     if !path_obj.exists() {
-        return Err(format!("图片不存在: {}", path));
+        return Err(ComicError::not_found(path));
     }
 
-    let data = fs::read(path).map_err(|e| format!("无法读取图片: {}", e))?;
+    let data = fs::read(path).map_err(|e| ComicError::io(path, e))?;
     let mime_type = get_mime_type(path);
     let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &data);
     
@@ -35,32 +153,30 @@ pub fn read_image_as_base64(path: &str) -> Result<String, String> {
 
 /// 读取图片文件并返回二进制数据（用于 Blob URL）
 /// 使用 BufReader 优化大文件读取性能
-pub fn read_image_as_bytes(path: &str) -> Result<Vec<u8>, String> {
-    use std::io::Read;
-    
+pub fn read_image_as_bytes(path: &str) -> Result<Vec<u8>, ComicError> {
     let path_obj = Path::new(path);
     
     if !path_obj.exists() {
-        return Err(format!("图片不存在: {}", path));
+        return Err(ComicError::not_found(path));
     }
 
     // 获取文件大小以预分配 Vec 容量
-    let metadata = fs::metadata(path).map_err(|e| format!("无法获取文件信息: {}", e))?;
+    let metadata = fs::metadata(path).map_err(|e| ComicError::io(path, e))?;
     let file_size = metadata.len() as usize;
     
     // 使用 BufReader 提高读取性能
-    let file = fs::File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
+    let file = fs::File::open(path).map_err(|e| ComicError::io(path, e))?;
     let mut reader = std::io::BufReader::with_capacity(8192 * 16, file); // 128KB 缓冲区
     
     // 预分配 Vec 容量避免多次重新分配
     let mut buffer = Vec::with_capacity(file_size);
-    reader.read_to_end(&mut buffer).map_err(|e| format!("无法读取图片: {}", e))?;
+    reader.read_to_end(&mut buffer).map_err(|e| ComicError::io(path, e))?;
     
     Ok(buffer)
 }
 
 /// 获取 MIME 类型
-fn get_mime_type(path: &str) -> &'static str {
+pub fn get_mime_type(path: &str) -> &'static str {
     let path = Path::new(path);
     match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
         Some("jpg") | Some("jpeg") => "image/jpeg",
@@ -73,74 +189,85 @@ fn get_mime_type(path: &str) -> &'static str {
     }
 }
 
-/// 分块读取大图片（用于超长条漫）
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ImageChunk {
-    pub index: usize,
-    pub x: u32,
-    pub y: u32,
-    pub width: u32,
-    pub height: u32,
-    pub data: String,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, ImageFormat};
+    use std::io::Cursor;
 
-/// 将图片分块
-pub fn split_image_to_chunks(
-    path: &str,
-    chunk_height: u32,
-) -> Result<Vec<ImageChunk>, String> {
-    let img = image::open(path).map_err(|e| format!("无法打开图片: {}", e))?;
-    
-    let width = img.width();
-    let height = img.height();
-    
-    // 如果图片不是很高，直接返回整张图片
-    if height <= chunk_height * 2 {
-        let base64_data = read_image_as_base64(path)?;
-        return Ok(vec![ImageChunk {
-            index: 0,
-            x: 0,
-            y: 0,
-            width,
-            height,
-            data: base64_data,
-        }]);
-    }
-
-    let mut chunks = Vec::new();
-    let mut y = 0u32;
-    let mut index = 0usize;
-
-    while y < height {
-        let current_height = (chunk_height).min(height - y);
-        
-        let chunk_img = img.crop_imm(0, y, width, current_height);
-        
-        let mut buffer = Vec::new();
-        chunk_img
-            .write_to(
-                &mut std::io::Cursor::new(&mut buffer),
-                image::ImageFormat::Jpeg,
-            )
-            .map_err(|e| format!("无法处理图片块: {}", e))?;
-        
-        let base64_data = base64::Engine::encode(
-            &base64::engine::general_purpose::STANDARD,
-            &buffer,
-        );
-        
-        chunks.push(ImageChunk {
-            index,
-            x: 0,
-            y,
-            width,
-            height: current_height,
-            data: format!("data:image/jpeg;base64,{}", base64_data),
-        });
-        
-        y += current_height;
-        index += 1;
-    }
-
-    Ok(chunks)
+    /// 记录已读取字节数的数据流
+    struct CountingReader {
+        inner: Cursor<Vec<u8>>,
+        read: usize,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read += n;
+            Ok(n)
+        }
+    }
+
+    /// 解析尺寸并返回读取的字节数
+    fn sniff(data: Vec<u8>) -> (Result<(u32, u32), ComicError>, usize) {
+        let mut reader = CountingReader {
+            inner: Cursor::new(data),
+            read: 0,
+        };
+        let result = get_image_dimensions_from_reader(&mut reader);
+        (result, reader.read)
+    }
+
+    /// 12x7 的 JPEG，在 SOI 之后插入总计约 padding 字节的 APP15 段，把尺寸信息推到后面
+    fn jpeg_with_padding(padding: usize) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        GrayImage::new(12, 7)
+            .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Jpeg)
+            .unwrap();
+
+        let mut data = encoded[..2].to_vec();
+        let mut remaining = padding;
+        while remaining > 0 {
+            let len = remaining.min(60_000);
+            data.extend_from_slice(&[0xFF, 0xEF]);
+            data.extend_from_slice(&(len as u16 + 2).to_be_bytes());
+            data.resize(data.len() + len, 0);
+            remaining -= len;
+        }
+        data.extend_from_slice(&encoded[2..]);
+        // 图片数据之后的额外内容，用于确认没有读取整个数据流
+        data.resize(data.len() + 512 * 1024, 0);
+        data
+    }
+
+    #[test]
+    fn small_header_is_parsed_from_the_first_chunk() {
+        let (result, read) = sniff(jpeg_with_padding(0));
+        assert_eq!(result.unwrap(), (12, 7));
+        assert_eq!(read, HEADER_SNIFF_SIZES[0]);
+    }
+
+    #[test]
+    fn large_header_falls_back_to_the_second_chunk() {
+        let (result, read) = sniff(jpeg_with_padding(100 * 1024));
+        assert_eq!(result.unwrap(), (12, 7));
+        assert_eq!(read, HEADER_SNIFF_SIZES[1]);
+    }
+
+    #[test]
+    fn header_beyond_the_sniff_sizes_reads_the_whole_stream() {
+        let data = jpeg_with_padding(300 * 1024);
+        let total = data.len();
+        let (result, read) = sniff(data);
+        assert_eq!(result.unwrap(), (12, 7));
+        assert_eq!(read, total);
+    }
+
+    #[test]
+    fn short_invalid_data_stops_at_end_of_stream() {
+        let (result, read) = sniff(vec![0x42; 100]);
+        assert!(result.is_err());
+        assert_eq!(read, 100);
+    }
 }
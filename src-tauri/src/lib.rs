@@ -1,191 +1,426 @@
+mod comic_info;
+mod comic_source;
+mod crop;
+mod epub_handler;
+mod error;
 mod file_system;
+mod i18n;
 mod image_handler;
+mod library;
+mod page_cache;
+mod pdf_handler;
+mod protocol;
+mod rar_handler;
+mod sevenz_handler;
+mod spread;
 mod storage;
+mod tar_handler;
+#[cfg(test)]
+mod test_support;
+mod thumbnail;
+mod tile;
+mod watcher;
 mod zip_handler;
 
-use file_system::{FileNode, scan_directory, get_images_in_directory};
-use image_handler::{ImageChunk, read_image_as_base64, read_image_as_bytes, get_image_dimensions, split_image_to_chunks};
+use comic_info::ComicInfo;
+use comic_source::{ComicMetadata, OpenedComic, SourceCache, open_comic, read_page, read_page_scaled, render_page, prefetch_page, get_comic_metadata, get_page_dimensions, save_comic_info};
+use crop::CropBox;
+use error::ComicError;
+use file_system::FileNode;
+use i18n::{set_language, tr, Msg};
+use image_handler::{read_image_as_base64, ScaleFilter};
+use library::{LibraryDb, LibraryEntry, scan_library, get_library_comics, get_page_dimensions_cached, get_page_crop_cached};
+use pdf_handler::{set_pdfium_library_dir, PDFIUM_RESOURCE_DIR};
+use protocol::{COMIC_PROTOCOL, handle_comic_request};
+use spread::{Spread, SpreadSplit, VirtualPage, get_spread_plan, get_virtual_pages};
 use storage::{
     AppData, AppDataCache, Bookmark, ReadingProgress, Settings, OpenedDirectory,
     load_app_data, save_progress, get_progress,
+    flush_progress, shutdown_progress_writer, set_progress_save_delay, start_progress_writer,
     add_bookmark, remove_bookmark, get_bookmarks, get_comic_bookmarks,
     save_settings, get_settings,
     save_opened_directory, remove_opened_directory, get_opened_directories,
 };
-use zip_handler::{ZipCache, ZipImageInfo, get_zip_image_list, read_zip_image, read_zip_image_bytes, get_zip_image_dimensions};
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager, RunEvent, WindowEvent};
+use thumbnail::{ThumbnailCache, get_cover_thumbnail, get_page_thumbnail};
+use tile::{DecodedImageCache, TileLayout, TileOptions, get_tile, get_tile_layout};
+use watcher::{LibraryWatcher, start_library_watcher, sync_watched_directories};
+
+/// 在阻塞线程池中执行读取压缩包、解码图片等操作（可能等待句柄池），避免占用异步运行时
+async fn run_blocking<T, F>(f: F) -> Result<T, ComicError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, ComicError> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| ComicError::Task { source: e.to_string() })?
+}
 
 // ============== 文件系统命令 ==============
 
-/// 扫描目录
+/// 扫描目录（增量：只重新统计 mtime/size 变化的漫画）
+/// 首次扫描需要打开每个压缩包统计页数，放到阻塞线程池中执行，避免占用异步运行时
 #[tauri::command]
-fn cmd_scan_directory(path: String, max_depth: u32) -> Result<FileNode, String> {
-    scan_directory(&path, 0, max_depth)
+async fn cmd_scan_directory(app: AppHandle, path: String, max_depth: u32) -> Result<FileNode, ComicError> {
+    run_blocking(move || scan_library(&app, &app.state::<LibraryDb>(), &path, max_depth)).await
 }
 
-/// 获取目录中的图片列表
+/// 获取漫画库中的所有漫画
 #[tauri::command]
-fn cmd_get_directory_images(path: String) -> Result<Vec<String>, String> {
-    get_images_in_directory(&path)
+fn cmd_get_library(app: AppHandle, library: tauri::State<LibraryDb>) -> Result<Vec<LibraryEntry>, ComicError> {
+    get_library_comics(&app, &library)
 }
 
-// ============== ZIP 命令 ==============
+// ============== 漫画命令 ==============
+// 文件夹与各类压缩包统一通过 ComicSource 读取
 
-/// 获取 ZIP 中的图片列表
+/// 打开漫画，返回元数据和页面列表
 #[tauri::command]
-fn cmd_get_zip_images(path: String) -> Result<Vec<ZipImageInfo>, String> {
-    get_zip_image_list(&path)
+fn cmd_open_comic(path: String, cache: tauri::State<SourceCache>) -> Result<OpenedComic, ComicError> {
+    open_comic(&path, &cache)
 }
 
-/// 读取 ZIP 中的图片
+/// 获取漫画元数据（包括 ComicInfo.xml）
 #[tauri::command]
-fn cmd_read_zip_image(zip_path: String, image_path: String, cache: tauri::State<ZipCache>) -> Result<String, String> {
-    read_zip_image(&zip_path, &image_path, &cache)
+fn cmd_get_comic_metadata(path: String, cache: tauri::State<SourceCache>) -> Result<ComicMetadata, ComicError> {
+    get_comic_metadata(&path, &cache)
 }
 
-// ============== 图片命令 ==============
+/// 写入 ComicInfo.xml（仅 ZIP/CBZ），返回重新打开后的漫画
+#[tauri::command]
+fn cmd_save_comic_info(
+    path: String,
+    comic_info: ComicInfo,
+    cache: tauri::State<SourceCache>,
+) -> Result<OpenedComic, ComicError> {
+    save_comic_info(&path, &comic_info, &cache)
+}
 
-/// 读取图片为 Base64
+/// 读取页面为二进制数据（用于 Blob URL）
 #[tauri::command]
-fn cmd_read_image(path: String) -> Result<String, String> {
-    read_image_as_base64(&path)
+async fn cmd_read_page(app: AppHandle, comic_path: String, page_index: usize) -> Result<Vec<u8>, ComicError> {
+    run_blocking(move || read_page(&comic_path, page_index, &app.state::<SourceCache>())).await
+}
+
+/// 读取按视口宽度缩小后的页面（减少前端下载和解码的数据量），filter 默认 Lanczos3
+#[tauri::command]
+async fn cmd_read_page_scaled(
+    app: AppHandle,
+    comic_path: String,
+    page_index: usize,
+    target_width: u32,
+    filter: Option<ScaleFilter>,
+) -> Result<Vec<u8>, ComicError> {
+    run_blocking(move || {
+        let cache = app.state::<SourceCache>();
+        read_page_scaled(&comic_path, page_index, target_width, filter.unwrap_or_default(), &cache)
+    })
+    .await
+}
+
+/// 获取页面去掉纯色边框后的区域（每页只检测一次，结果记录在漫画库中）
+#[tauri::command]
+async fn cmd_get_page_crop(app: AppHandle, comic_path: String, page_index: usize) -> Result<CropBox, ComicError> {
+    run_blocking(move || {
+        get_page_crop_cached(&app, &app.state::<LibraryDb>(), &app.state::<SourceCache>(), &comic_path, page_index)
+    })
+    .await
+}
+
+/// 获取虚拟页列表：split 省略时使用设置中的跨页拆分方式
+#[tauri::command]
+async fn cmd_get_virtual_pages(
+    app: AppHandle,
+    comic_path: String,
+    split: Option<SpreadSplit>,
+) -> Result<Vec<VirtualPage>, ComicError> {
+    run_blocking(move || {
+        let split = match split {
+            Some(split) => split,
+            None => get_settings(&app, &app.state::<AppDataCache>())?.spread_split,
+        };
+        get_virtual_pages(&app, &app.state::<LibraryDb>(), &app.state::<SourceCache>(), &comic_path, split)
+    })
+    .await
+}
+
+/// 书本模式：把页面分组为单页/双页展开，right_to_left 省略时按漫画的 Manga 字段决定
+#[tauri::command]
+async fn cmd_plan_spreads(
+    app: AppHandle,
+    comic_path: String,
+    offset: Option<bool>,
+    right_to_left: Option<bool>,
+) -> Result<Vec<Spread>, ComicError> {
+    run_blocking(move || {
+        let (library, cache) = (app.state::<LibraryDb>(), app.state::<SourceCache>());
+        get_spread_plan(&app, &library, &cache, &comic_path, offset.unwrap_or(false), right_to_left)
+    })
+    .await
 }
 
-/// 读取图片为二进制数据（用于 Blob URL）
-/// 使用 async 让文件读取在后台线程执行，不阻塞主线程
+/// 批量获取所有页面尺寸（只解析文件头，结果缓存在漫画库中），失败的页面为 null
 #[tauri::command]
-async fn cmd_read_image_bytes(path: String) -> Result<Vec<u8>, String> {
-    // 在独立的线程池中执行文件读取，避免阻塞主线程
-    tokio::task::spawn_blocking(move || {
-        read_image_as_bytes(&path)
+async fn cmd_get_all_page_dimensions(app: AppHandle, comic_path: String) -> Result<Vec<Option<(u32, u32)>>, ComicError> {
+    run_blocking(move || {
+        get_page_dimensions_cached(&app, &app.state::<LibraryDb>(), &app.state::<SourceCache>(), &comic_path)
     })
     .await
-    .map_err(|e| format!("任务执行失败: {}", e))?
 }
 
-/// 读取 ZIP 中的图片为二进制数据（用于 Blob URL）
-/// 使用 async 让文件读取在后台线程执行，不阻塞主线程
+/// 在后台预读页面到内存缓存，count 未指定时使用设置中的预加载数量
+/// 每页单独提交到后台线程，同一漫画最多按句柄池上限并行解压
 #[tauri::command]
-async fn cmd_read_zip_image_bytes(zip_path: String, image_path: String, cache: tauri::State<'_, ZipCache>) -> Result<Vec<u8>, String> {
-    // 由于 cache 有生命周期限制，我们需要在这里直接调用
-    // Tauri 的 async 命令本身就会在后台执行
-    read_zip_image_bytes(&zip_path, &image_path, &cache)
+fn cmd_prefetch_pages(
+    app: AppHandle,
+    data_cache: tauri::State<AppDataCache>,
+    comic_path: String,
+    from: usize,
+    count: Option<usize>,
+) -> Result<(), ComicError> {
+    let count = match count {
+        Some(count) => count,
+        None => get_settings(&app, &data_cache)?.preload_count as usize,
+    };
+
+    for page_index in from..from.saturating_add(count) {
+        let app = app.clone();
+        let comic_path = comic_path.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            // 预读失败不影响正常读取，忽略即可
+            let _ = prefetch_page(&comic_path, page_index, &app.state::<SourceCache>());
+        });
+    }
+
+    Ok(())
 }
 
-/// 获取图片尺寸
+/// 获取页面尺寸
 #[tauri::command]
-fn cmd_get_image_dimensions(path: String) -> Result<(u32, u32), String> {
-    get_image_dimensions(&path)
+fn cmd_get_page_dimensions(comic_path: String, page_index: usize, cache: tauri::State<SourceCache>) -> Result<(u32, u32), ComicError> {
+    get_page_dimensions(&comic_path, page_index, &cache)
 }
 
-/// 获取 ZIP 中图片的尺寸
+/// 按指定宽度或 DPI 渲染 PDF 页面（JPEG 二进制数据）
 #[tauri::command]
-fn cmd_get_zip_image_dimensions(zip_path: String, image_path: String, cache: tauri::State<ZipCache>) -> Result<(u32, u32), String> {
-    get_zip_image_dimensions(&zip_path, &image_path, &cache)
+async fn cmd_render_pdf_page(app: AppHandle, path: String, page_index: usize, target_width: Option<u32>, dpi: Option<f32>) -> Result<Vec<u8>, ComicError> {
+    // 文档由句柄池保持打开
+    run_blocking(move || render_page(&path, page_index, target_width, dpi, &app.state::<SourceCache>())).await
+}
+
+// ============== 缩略图命令 ==============
+
+/// 获取漫画封面缩略图（JPEG），结果缓存在磁盘
+#[tauri::command]
+async fn cmd_get_cover_thumbnail(app: AppHandle, comic_path: String, max_size: u32) -> Result<Vec<u8>, ComicError> {
+    run_blocking(move || {
+        let (thumbnails, cache) = (app.state::<ThumbnailCache>(), app.state::<SourceCache>());
+        get_cover_thumbnail(&app, &thumbnails, &cache, &comic_path, max_size)
+    })
+    .await
 }
 
-/// 分块读取图片
+/// 获取页面缩略图（JPEG），结果缓存在磁盘
 #[tauri::command]
-fn cmd_split_image(path: String, chunk_height: u32) -> Result<Vec<ImageChunk>, String> {
-    split_image_to_chunks(&path, chunk_height)
+async fn cmd_get_page_thumbnail(
+    app: AppHandle,
+    comic_path: String,
+    page_index: usize,
+    max_size: u32,
+) -> Result<Vec<u8>, ComicError> {
+    run_blocking(move || {
+        let (thumbnails, cache) = (app.state::<ThumbnailCache>(), app.state::<SourceCache>());
+        get_page_thumbnail(&app, &thumbnails, &cache, &comic_path, page_index, max_size)
+    })
+    .await
+}
+
+// ============== 图片命令 ==============
+
+/// 读取图片为 Base64
+#[tauri::command]
+fn cmd_read_image(path: String) -> Result<String, ComicError> {
+    read_image_as_base64(&path)
+}
+
+/// 获取超长页面的分块布局（只读取文件头），前端据此占位后按需请求分块
+#[tauri::command]
+async fn cmd_get_tile_layout(
+    app: AppHandle,
+    comic_path: String,
+    page_index: usize,
+    tile_height: Option<u32>,
+) -> Result<TileLayout, ComicError> {
+    run_blocking(move || get_tile_layout(&comic_path, page_index, tile_height, &app.state::<SourceCache>())).await
+}
+
+/// 获取单个分块（默认 JPEG，可选 PNG / 无损 WebP），同一页面只解码一次
+#[tauri::command]
+async fn cmd_get_tile(
+    app: AppHandle,
+    comic_path: String,
+    page_index: usize,
+    index: usize,
+    tile_height: Option<u32>,
+    options: Option<TileOptions>,
+) -> Result<Vec<u8>, ComicError> {
+    run_blocking(move || {
+        let options = options.unwrap_or_default();
+        let (cache, decoded) = (app.state::<SourceCache>(), app.state::<DecodedImageCache>());
+        get_tile(&comic_path, page_index, index, tile_height, &options, &cache, &decoded)
+    })
+    .await
 }
 
 // ============== 存储命令 ==============
 
 /// 保存阅读进度
 #[tauri::command]
-fn cmd_save_progress(app: AppHandle, cache: tauri::State<AppDataCache>, progress: ReadingProgress) -> Result<(), String> {
+fn cmd_save_progress(app: AppHandle, cache: tauri::State<AppDataCache>, progress: ReadingProgress) -> Result<(), ComicError> {
     save_progress(&app, &cache, progress)
 }
 
 /// 获取阅读进度
 #[tauri::command]
-fn cmd_get_progress(app: AppHandle, cache: tauri::State<AppDataCache>, comic_path: String) -> Result<Option<ReadingProgress>, String> {
+fn cmd_get_progress(app: AppHandle, cache: tauri::State<AppDataCache>, comic_path: String) -> Result<Option<ReadingProgress>, ComicError> {
     get_progress(&app, &cache, &comic_path)
 }
 
 /// 添加书签
 #[tauri::command]
-fn cmd_add_bookmark(app: AppHandle, cache: tauri::State<AppDataCache>, bookmark: Bookmark) -> Result<(), String> {
+fn cmd_add_bookmark(app: AppHandle, cache: tauri::State<AppDataCache>, bookmark: Bookmark) -> Result<(), ComicError> {
     add_bookmark(&app, &cache, bookmark)
 }
 
 /// 删除书签
 #[tauri::command]
-fn cmd_remove_bookmark(app: AppHandle, cache: tauri::State<AppDataCache>, bookmark_id: String) -> Result<(), String> {
+fn cmd_remove_bookmark(app: AppHandle, cache: tauri::State<AppDataCache>, bookmark_id: String) -> Result<(), ComicError> {
     remove_bookmark(&app, &cache, &bookmark_id)
 }
 
 /// 获取所有书签
 #[tauri::command]
-fn cmd_get_bookmarks(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<Vec<Bookmark>, String> {
+fn cmd_get_bookmarks(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<Vec<Bookmark>, ComicError> {
     get_bookmarks(&app, &cache)
 }
 
 /// 获取漫画的书签
 #[tauri::command]
-fn cmd_get_comic_bookmarks(app: AppHandle, cache: tauri::State<AppDataCache>, comic_path: String) -> Result<Vec<Bookmark>, String> {
+fn cmd_get_comic_bookmarks(app: AppHandle, cache: tauri::State<AppDataCache>, comic_path: String) -> Result<Vec<Bookmark>, ComicError> {
     get_comic_bookmarks(&app, &cache, &comic_path)
 }
 
 /// 保存设置
 #[tauri::command]
-fn cmd_save_settings(app: AppHandle, cache: tauri::State<AppDataCache>, settings: Settings) -> Result<(), String> {
+fn cmd_save_settings(
+    app: AppHandle,
+    cache: tauri::State<AppDataCache>,
+    sources: tauri::State<SourceCache>,
+    settings: Settings,
+) -> Result<(), ComicError> {
+    sources.pages.set_budget_mb(settings.page_cache_size_mb);
+    set_progress_save_delay(&cache, settings.progress_save_delay_ms);
+    set_language(settings.language);
     save_settings(&app, &cache, settings)
 }
 
 /// 获取设置
 #[tauri::command]
-fn cmd_get_settings(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<Settings, String> {
+fn cmd_get_settings(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<Settings, ComicError> {
     get_settings(&app, &cache)
 }
 
 /// 保存打开的目录
 #[tauri::command]
-fn cmd_save_opened_directory(app: AppHandle, cache: tauri::State<AppDataCache>, path: String) -> Result<(), String> {
-    save_opened_directory(&app, &cache, &path)
+fn cmd_save_opened_directory(app: AppHandle, cache: tauri::State<AppDataCache>, path: String) -> Result<(), ComicError> {
+    save_opened_directory(&app, &cache, &path)?;
+    sync_watched_directories(&app)
 }
 
 /// 移除打开的目录
 #[tauri::command]
-fn cmd_remove_opened_directory(app: AppHandle, cache: tauri::State<AppDataCache>, path: String) -> Result<(), String> {
-    remove_opened_directory(&app, &cache, &path)
+fn cmd_remove_opened_directory(app: AppHandle, cache: tauri::State<AppDataCache>, path: String) -> Result<(), ComicError> {
+    remove_opened_directory(&app, &cache, &path)?;
+    sync_watched_directories(&app)
 }
 
 /// 获取所有打开的目录
 #[tauri::command]
-fn cmd_get_opened_directories(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<Vec<OpenedDirectory>, String> {
+fn cmd_get_opened_directories(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<Vec<OpenedDirectory>, ComicError> {
     get_opened_directories(&app, &cache)
 }
 
 /// 加载所有应用数据
 #[tauri::command]
-fn cmd_load_app_data(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<AppData, String> {
+fn cmd_load_app_data(app: AppHandle, cache: tauri::State<AppDataCache>) -> Result<AppData, ComicError> {
     load_app_data(&app, &cache)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
-        .manage(ZipCache::default())
+        .manage(SourceCache::default())
         .manage(AppDataCache::default())
+        .manage(LibraryDb::default())
+        .manage(LibraryWatcher::default())
+        .manage(ThumbnailCache::default())
+        .manage(DecodedImageCache::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        // 页面直接通过 comic:// 协议加载，避免经过 IPC 序列化
+        .register_asynchronous_uri_scheme_protocol(COMIC_PROTOCOL, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(handle_comic_request(&app, &request));
+            });
+        })
+        .setup(|app| {
+            // PDF 渲染使用随程序打包在资源目录中的 pdfium
+            if let Ok(resource_dir) = app.path().resource_dir() {
+                set_pdfium_library_dir(resource_dir.join(PDFIUM_RESOURCE_DIR));
+            }
+
+            // 按设置调整页面缓存的内存预算、进度写入窗口和后端消息语言
+            let data_cache = app.state::<AppDataCache>();
+            if let Ok(settings) = get_settings(app.handle(), &data_cache) {
+                app.state::<SourceCache>().pages.set_budget_mb(settings.page_cache_size_mb);
+                set_progress_save_delay(&data_cache, settings.progress_save_delay_ms);
+                set_language(settings.language);
+            }
+            start_progress_writer(app.handle());
+
+            // 监听失败不影响正常使用，只是目录树需要手动刷新
+            if let Err(e) = start_library_watcher(app.handle()) {
+                eprintln!("{}", tr(Msg::Warning, &[&e]));
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // 文件系统
             cmd_scan_directory,
-            cmd_get_directory_images,
-            // ZIP
-            cmd_get_zip_images,
-            cmd_read_zip_image,
-            cmd_read_zip_image_bytes,
-            cmd_get_zip_image_dimensions,
+            cmd_get_library,
+            // 漫画
+            cmd_open_comic,
+            cmd_get_comic_metadata,
+            cmd_save_comic_info,
+            cmd_read_page,
+            cmd_read_page_scaled,
+            cmd_get_page_crop,
+            cmd_get_virtual_pages,
+            cmd_plan_spreads,
+            cmd_prefetch_pages,
+            cmd_get_page_dimensions,
+            cmd_get_all_page_dimensions,
+            cmd_render_pdf_page,
+            // 缩略图
+            cmd_get_cover_thumbnail,
+            cmd_get_page_thumbnail,
             // 图片
             cmd_read_image,
-            cmd_read_image_bytes,
-            cmd_get_image_dimensions,
-            cmd_split_image,
+            cmd_get_tile_layout,
+            cmd_get_tile,
             // 存储
             cmd_save_progress,
             cmd_get_progress,
@@ -200,6 +435,25 @@ pub fn run() {
             cmd_get_opened_directories,
             cmd_load_app_data,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            // 关闭窗口和请求退出时先把队列中的阅读进度写入数据库
+            RunEvent::ExitRequested { .. }
+            | RunEvent::WindowEvent {
+                event: WindowEvent::CloseRequested { .. },
+                ..
+            } => {
+                if let Err(e) = flush_progress(app, &app.state::<AppDataCache>()) {
+                    eprintln!("{}", tr(Msg::Warning, &[&e]));
+                }
+            }
+            // 退出时停止后台写入线程，等待正在进行的写入完成后写入剩余进度
+            RunEvent::Exit => {
+                if let Err(e) = shutdown_progress_writer(app, &app.state::<AppDataCache>()) {
+                    eprintln!("{}", tr(Msg::Warning, &[&e]));
+                }
+            }
+            _ => {}
+        });
 }
@@ -0,0 +1,491 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+use crate::comic_source::{get_all_page_dimensions, get_page_info, read_page_entry, SourceCache};
+use crate::crop::{detect_crop_box_from_bytes, CropBox};
+use crate::error::ComicError;
+use crate::file_system::{comic_stamp, page_stamp, scan_directory, ArchiveKind, FileNode, FileStamp};
+use crate::spread::detect_gutter_from_bytes;
+use crate::storage::{ensure_data_dir, get_data_dir};
+
+/// 漫画库数据库文件名，与 comic_data.json 放在同一目录
+const LIBRARY_DB_FILE: &str = "comic_library.db";
+
+/// 数据库表结构版本（PRAGMA user_version）
+/// 版本 1 起页面裁剪和中缝结果按条目路径记录（页码会随 ComicInfo 排序或文件夹内容变化）
+const LIBRARY_SCHEMA_VERSION: u32 = 1;
+
+/// 漫画库数据库连接，首次使用时打开
+pub struct LibraryDb(pub Mutex<Option<Connection>>);
+
+impl Default for LibraryDb {
+    fn default() -> Self {
+        Self(Mutex::new(None))
+    }
+}
+
+/// 漫画库条目：扫描过的目录和漫画文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub path: String,
+    pub name: String,
+    #[serde(rename = "isDirectory")]
+    pub is_directory: bool,
+    #[serde(rename = "isComic")]
+    pub is_comic: bool,
+    #[serde(rename = "archiveKind")]
+    pub archive_kind: Option<ArchiveKind>,
+    #[serde(rename = "pageCount")]
+    pub page_count: u32,
+    pub size: u64,
+    /// 修改时间（毫秒时间戳）
+    pub mtime: i64,
+    #[serde(rename = "scannedAt")]
+    pub scanned_at: u64,
+}
+
+/// ArchiveKind 在数据库中的存储值
+fn archive_kind_to_str(kind: ArchiveKind) -> &'static str {
+    match kind {
+        ArchiveKind::Zip => "zip",
+        ArchiveKind::Rar => "rar",
+        ArchiveKind::SevenZip => "7z",
+        ArchiveKind::Tar => "tar",
+        ArchiveKind::Pdf => "pdf",
+        ArchiveKind::Epub => "epub",
+    }
+}
+
+fn archive_kind_from_str(value: &str) -> Option<ArchiveKind> {
+    match value {
+        "zip" => Some(ArchiveKind::Zip),
+        "rar" => Some(ArchiveKind::Rar),
+        "7z" => Some(ArchiveKind::SevenZip),
+        "tar" => Some(ArchiveKind::Tar),
+        "pdf" => Some(ArchiveKind::Pdf),
+        "epub" => Some(ArchiveKind::Epub),
+        _ => None,
+    }
+}
+
+/// 当前时间戳（毫秒）
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 页面缓存在库中记录的大小和修改时间（毫秒）
+fn stamp_columns(stamp: FileStamp) -> (i64, i64) {
+    (stamp.size as i64, stamp.mtime_millis())
+}
+
+/// 打开数据库并初始化表结构
+fn open_database(app: &AppHandle) -> Result<Connection, ComicError> {
+    ensure_data_dir(app)?;
+    let db_path = get_data_dir(app).join(LIBRARY_DB_FILE);
+    let conn = Connection::open(&db_path)?;
+
+    // 旧版按页码记录的裁剪和中缝结果只是缓存，直接丢弃后重新检测
+    let schema_version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if schema_version < 1 {
+        conn.execute_batch(
+            "DROP TABLE IF EXISTS page_crops;
+             DROP TABLE IF EXISTS page_gutters;",
+        )?;
+    }
+
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         CREATE TABLE IF NOT EXISTS library_entries (
+             path         TEXT PRIMARY KEY,
+             name         TEXT NOT NULL,
+             is_directory INTEGER NOT NULL,
+             is_comic     INTEGER NOT NULL,
+             archive_kind TEXT,
+             page_count   INTEGER NOT NULL,
+             size         INTEGER NOT NULL,
+             mtime        INTEGER NOT NULL,
+             scanned_at   INTEGER NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS page_dimensions (
+             path       TEXT PRIMARY KEY,
+             size       INTEGER NOT NULL,
+             mtime      INTEGER NOT NULL,
+             dimensions TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS page_crops (
+             path   TEXT NOT NULL,
+             entry  TEXT NOT NULL,
+             size   INTEGER NOT NULL,
+             mtime  INTEGER NOT NULL,
+             x      INTEGER NOT NULL,
+             y      INTEGER NOT NULL,
+             width  INTEGER NOT NULL,
+             height INTEGER NOT NULL,
+             PRIMARY KEY (path, entry)
+         );
+         CREATE TABLE IF NOT EXISTS page_gutters (
+             path   TEXT NOT NULL,
+             entry  TEXT NOT NULL,
+             size   INTEGER NOT NULL,
+             mtime  INTEGER NOT NULL,
+             gutter INTEGER NOT NULL,
+             PRIMARY KEY (path, entry)
+         );",
+    )?;
+    conn.pragma_update(None, "user_version", LIBRARY_SCHEMA_VERSION)?;
+
+    Ok(conn)
+}
+
+/// 获取数据库连接（首次使用时打开）
+pub fn with_library<T>(
+    app: &AppHandle,
+    db: &LibraryDb,
+    f: impl FnOnce(&mut Connection) -> Result<T, ComicError>,
+) -> Result<T, ComicError> {
+    let mut guard = db.0.lock()?;
+
+    if guard.is_none() {
+        *guard = Some(open_database(app)?);
+    }
+
+    f(guard.as_mut().unwrap())
+}
+
+/// 从查询结果行构造条目
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<LibraryEntry> {
+    let archive_kind: Option<String> = row.get(4)?;
+    Ok(LibraryEntry {
+        path: row.get(0)?,
+        name: row.get(1)?,
+        is_directory: row.get(2)?,
+        is_comic: row.get(3)?,
+        archive_kind: archive_kind.as_deref().and_then(archive_kind_from_str),
+        page_count: row.get(5)?,
+        size: row.get::<_, i64>(6)? as u64,
+        mtime: row.get(7)?,
+        scanned_at: row.get::<_, i64>(8)? as u64,
+    })
+}
+
+const SELECT_COLUMNS: &str =
+    "SELECT path, name, is_directory, is_comic, archive_kind, page_count, size, mtime, scanned_at FROM library_entries";
+
+/// 读取某个根目录下（含根目录本身）的所有条目
+fn load_entries_under(conn: &Connection, root: &str) -> Result<Vec<LibraryEntry>, ComicError> {
+    let sql = format!("{} WHERE substr(path, 1, length(?1)) = ?1", SELECT_COLUMNS);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params![root], entry_from_row)?;
+
+    let root_path = Path::new(root);
+    Ok(rows
+        .flatten()
+        // 前缀匹配会包含同名前缀的兄弟目录，按路径组件再过滤一次
+        .filter(|entry| Path::new(&entry.path).starts_with(root_path))
+        .collect())
+}
+
+/// 扫描上下文：记录已知条目，只重新检查 mtime/size 变化的路径
+pub struct LibraryScan {
+    known: HashMap<String, LibraryEntry>,
+    seen: HashSet<String>,
+    updated: Vec<LibraryEntry>,
+}
+
+impl LibraryScan {
+    fn new(entries: Vec<LibraryEntry>) -> Self {
+        Self {
+            known: entries.into_iter().map(|e| (e.path.clone(), e)).collect(),
+            seen: HashSet::new(),
+            updated: Vec::new(),
+        }
+    }
+
+    /// 获取页数：mtime 和 size 未变化时使用库中记录，否则调用 count 重新统计
+    pub fn page_count(
+        &mut self,
+        path: &Path,
+        archive_kind: Option<ArchiveKind>,
+        count: impl FnOnce() -> u32,
+    ) -> u32 {
+        let path_str = path.to_string_lossy().to_string();
+        self.seen.insert(path_str.clone());
+
+        // 文件夹汇总其中各图片的大小和修改时间，原地替换图片后也会重新统计
+        let Some(stamp) = comic_stamp(&path_str) else {
+            return count();
+        };
+        let (size, mtime) = (stamp.size, stamp.mtime_millis());
+
+        if let Some(entry) = self.known.get(&path_str) {
+            if entry.size == size && entry.mtime == mtime {
+                return entry.page_count;
+            }
+        }
+
+        let page_count = count();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path_str.clone());
+        let is_directory = path.is_dir();
+
+        self.updated.push(LibraryEntry {
+            path: path_str,
+            name,
+            is_directory,
+            is_comic: archive_kind.is_some() || (is_directory && page_count > 0),
+            archive_kind,
+            page_count,
+            size,
+            mtime,
+            scanned_at: now_millis(),
+        });
+
+        page_count
+    }
+
+    /// 本次扫描未访问且已不存在的条目
+    fn removed(&self) -> Vec<String> {
+        self.known
+            .keys()
+            .filter(|path| !self.seen.contains(*path) && !Path::new(path).exists())
+            .cloned()
+            .collect()
+    }
+}
+
+/// 写入扫描结果：更新变化的条目，删除已不存在的条目
+fn commit_scan(conn: &mut Connection, scan: &LibraryScan) -> Result<(), ComicError> {
+    let tx = conn.transaction()?;
+
+    {
+        let mut upsert = tx
+            .prepare(
+                "INSERT OR REPLACE INTO library_entries
+                 (path, name, is_directory, is_comic, archive_kind, page_count, size, mtime, scanned_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+
+        for entry in &scan.updated {
+            upsert
+                .execute(params![
+                    entry.path,
+                    entry.name,
+                    entry.is_directory,
+                    entry.is_comic,
+                    entry.archive_kind.map(archive_kind_to_str),
+                    entry.page_count,
+                    entry.size as i64,
+                    entry.mtime,
+                    entry.scanned_at as i64,
+                ])?;
+        }
+
+        let mut delete = tx.prepare("DELETE FROM library_entries WHERE path = ?1")?;
+
+        for path in scan.removed() {
+            delete.execute(params![path])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// 增量扫描目录：未变化的漫画直接使用库中的页数，扫描结果写回漫画库
+pub fn scan_library(app: &AppHandle, db: &LibraryDb, path: &str, max_depth: u32) -> Result<FileNode, ComicError> {
+    let known = with_library(app, db, |conn| load_entries_under(conn, path))?;
+    let mut scan = LibraryScan::new(known);
+
+    // 扫描期间不持有数据库锁
+    let tree = scan_directory(path, 0, max_depth, &mut scan)?;
+
+    with_library(app, db, |conn| commit_scan(conn, &scan))?;
+    Ok(tree)
+}
+
+/// 获取漫画库中的所有漫画
+pub fn get_library_comics(app: &AppHandle, db: &LibraryDb) -> Result<Vec<LibraryEntry>, ComicError> {
+    with_library(app, db, |conn| {
+        let sql = format!("{} WHERE is_comic = 1 ORDER BY path", SELECT_COLUMNS);
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], entry_from_row)?;
+        Ok(rows.flatten().collect())
+    })
+}
+
+/// 查询单个条目
+pub fn get_library_entry(app: &AppHandle, db: &LibraryDb, path: &str) -> Result<Option<LibraryEntry>, ComicError> {
+    with_library(app, db, |conn| {
+        let sql = format!("{} WHERE path = ?1", SELECT_COLUMNS);
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query_map(params![path], entry_from_row)?;
+        Ok(rows.next().and_then(|row| row.ok()))
+    })
+}
+
+/// 删除路径及其下的所有条目，返回该路径原有的条目
+pub fn remove_library_entries(app: &AppHandle, db: &LibraryDb, path: &str) -> Result<Option<LibraryEntry>, ComicError> {
+    with_library(app, db, |conn| {
+        let entries = load_entries_under(conn, path)?;
+        let tx = conn.transaction()?;
+
+        for entry in &entries {
+            tx.execute("DELETE FROM library_entries WHERE path = ?1", params![entry.path])?;
+        }
+
+        tx.commit()?;
+        Ok(entries.into_iter().find(|entry| entry.path == path))
+    })
+}
+
+/// 获取所有页面尺寸：漫画未变化时直接使用库中记录，否则只解析各页文件头并写回
+pub fn get_page_dimensions_cached(
+    app: &AppHandle,
+    db: &LibraryDb,
+    sources: &SourceCache,
+    comic_path: &str,
+) -> Result<Vec<Option<(u32, u32)>>, ComicError> {
+    // 文件夹漫画汇总其中各图片，原地替换图片后也会重新读取
+    let stamp = comic_stamp(comic_path).map(stamp_columns);
+
+    if let Some((size, mtime)) = stamp {
+        let cached = with_library(app, db, |conn| {
+            let row: Option<String> = conn
+                .query_row(
+                    "SELECT dimensions FROM page_dimensions WHERE path = ?1 AND size = ?2 AND mtime = ?3",
+                    params![comic_path, size, mtime],
+                    |row| row.get(0),
+                )
+                .ok();
+            Ok(row)
+        })?;
+
+        if let Some(dimensions) = cached.and_then(|json| serde_json::from_str(&json).ok()) {
+            return Ok(dimensions);
+        }
+    }
+
+    let dimensions = get_all_page_dimensions(comic_path, sources)?;
+
+    if let Some((size, mtime)) = stamp {
+        let json = serde_json::to_string(&dimensions)?;
+        with_library(app, db, |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO page_dimensions (path, size, mtime, dimensions) VALUES (?1, ?2, ?3, ?4)",
+                params![comic_path, size, mtime, json],
+            )?;
+            Ok(())
+        })?;
+    }
+
+    Ok(dimensions)
+}
+
+/// 获取页面的边框裁剪区域：每页只检测一次，结果按条目路径记录在库中，页面所在文件（文件夹漫画为图片本身）的大小或修改时间变化后重新检测
+pub fn get_page_crop_cached(
+    app: &AppHandle,
+    db: &LibraryDb,
+    sources: &SourceCache,
+    comic_path: &str,
+    page_index: usize,
+) -> Result<CropBox, ComicError> {
+    let page = get_page_info(comic_path, page_index, sources)?;
+    let stamp = page_stamp(comic_path, &page.path).map(stamp_columns);
+
+    if let Some((size, mtime)) = stamp {
+        let cached = with_library(app, db, |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT x, y, width, height FROM page_crops
+                     WHERE path = ?1 AND entry = ?2 AND size = ?3 AND mtime = ?4",
+                    params![comic_path, page.path, size, mtime],
+                    |row| {
+                        Ok(CropBox {
+                            x: row.get(0)?,
+                            y: row.get(1)?,
+                            width: row.get(2)?,
+                            height: row.get(3)?,
+                        })
+                    },
+                )
+                .ok();
+            Ok(row)
+        })?;
+
+        if let Some(crop) = cached {
+            return Ok(crop);
+        }
+    }
+
+    let (_, buffer) = read_page_entry(comic_path, page_index, sources)?;
+    let crop = detect_crop_box_from_bytes(&buffer)?;
+
+    if let Some((size, mtime)) = stamp {
+        with_library(app, db, |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO page_crops (path, entry, size, mtime, x, y, width, height)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![comic_path, page.path, size, mtime, crop.x, crop.y, crop.width, crop.height],
+            )?;
+            Ok(())
+        })?;
+    }
+
+    Ok(crop)
+}
+
+/// 页面中间是否有中缝：需要解码整页，每页只检测一次，结果与裁剪区域一样按条目路径记录在库中
+pub fn get_page_gutter_cached(
+    app: &AppHandle,
+    db: &LibraryDb,
+    sources: &SourceCache,
+    comic_path: &str,
+    page_index: usize,
+) -> Result<bool, ComicError> {
+    let page = get_page_info(comic_path, page_index, sources)?;
+    let stamp = page_stamp(comic_path, &page.path).map(stamp_columns);
+
+    if let Some((size, mtime)) = stamp {
+        let cached = with_library(app, db, |conn| {
+            let row: Option<bool> = conn
+                .query_row(
+                    "SELECT gutter FROM page_gutters
+                     WHERE path = ?1 AND entry = ?2 AND size = ?3 AND mtime = ?4",
+                    params![comic_path, page.path, size, mtime],
+                    |row| row.get(0),
+                )
+                .ok();
+            Ok(row)
+        })?;
+
+        if let Some(gutter) = cached {
+            return Ok(gutter);
+        }
+    }
+
+    let (_, buffer) = read_page_entry(comic_path, page_index, sources)?;
+    let gutter = detect_gutter_from_bytes(&buffer)?;
+
+    if let Some((size, mtime)) = stamp {
+        with_library(app, db, |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO page_gutters (path, entry, size, mtime, gutter)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![comic_path, page.path, size, mtime, gutter],
+            )?;
+            Ok(())
+        })?;
+    }
+
+    Ok(gutter)
+}
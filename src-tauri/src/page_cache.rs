@@ -0,0 +1,172 @@
+use lru::LruCache;
+use std::sync::{Arc, Mutex};
+
+use crate::file_system::{page_stamp, FileStamp};
+
+/// 默认内存预算（MB）
+pub const DEFAULT_PAGE_CACHE_MB: u32 = 256;
+
+/// 页面缓存键：漫画路径 + 条目路径 + 页面所在文件的大小和修改时间 + 变体（如缩放尺寸，原图为空）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageKey {
+    comic_path: String,
+    entry: String,
+    stamp: FileStamp,
+    variant: String,
+}
+
+impl PageKey {
+    /// 漫画文件（文件夹漫画为页面图片本身）被改写后标记变化，旧数据不会再被命中
+    pub fn new(comic_path: &str, entry: &str) -> Self {
+        Self {
+            comic_path: comic_path.to_string(),
+            entry: entry.to_string(),
+            stamp: page_stamp(comic_path, entry).unwrap_or_default(),
+            variant: String::new(),
+        }
+    }
+
+    /// 同一页面的处理结果（如缩放后的图片）使用不同的变体分别缓存
+    pub fn with_variant(mut self, variant: String) -> Self {
+        self.variant = variant;
+        self
+    }
+}
+
+struct PageCacheInner {
+    entries: LruCache<PageKey, Arc<Vec<u8>>>,
+    total_bytes: usize,
+    budget_bytes: usize,
+}
+
+impl PageCacheInner {
+    /// 按 LRU 顺序淘汰，直到不超过预算
+    fn shrink(&mut self) {
+        while self.total_bytes > self.budget_bytes {
+            match self.entries.pop_lru() {
+                Some((_, data)) => self.total_bytes -= data.len(),
+                None => break,
+            }
+        }
+    }
+}
+
+/// 解压后的页面数据缓存，按字节数而不是条目数限制内存占用
+pub struct PageCache(Mutex<PageCacheInner>);
+
+impl Default for PageCache {
+    fn default() -> Self {
+        Self(Mutex::new(PageCacheInner {
+            entries: LruCache::unbounded(),
+            total_bytes: 0,
+            budget_bytes: DEFAULT_PAGE_CACHE_MB as usize * 1024 * 1024,
+        }))
+    }
+}
+
+impl PageCache {
+    /// 读取缓存的页面数据（会更新访问顺序）
+    pub fn get(&self, key: &PageKey) -> Option<Arc<Vec<u8>>> {
+        let mut inner = self.0.lock().ok()?;
+        inner.entries.get(key).cloned()
+    }
+
+    /// 是否已缓存（不更新访问顺序）
+    pub fn contains(&self, key: &PageKey) -> bool {
+        self.0
+            .lock()
+            .map(|inner| inner.entries.contains(key))
+            .unwrap_or(false)
+    }
+
+    /// 写入页面数据，超过预算的单个页面不缓存
+    pub fn insert(&self, key: PageKey, data: Arc<Vec<u8>>) {
+        let Ok(mut inner) = self.0.lock() else {
+            return;
+        };
+        if data.len() > inner.budget_bytes {
+            return;
+        }
+
+        inner.total_bytes += data.len();
+        if let Some(old) = inner.entries.put(key, data) {
+            inner.total_bytes -= old.len();
+        }
+        inner.shrink();
+    }
+
+    /// 设置内存预算（MB），0 表示禁用缓存
+    pub fn set_budget_mb(&self, budget_mb: u32) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.budget_bytes = budget_mb as usize * 1024 * 1024;
+            inner.shrink();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(entry: &str) -> PageKey {
+        PageKey::new("/missing/comic.cbz", entry)
+    }
+
+    /// 预算为 budget 字节的缓存
+    fn cache_with_budget(budget: usize) -> PageCache {
+        let cache = PageCache::default();
+        cache.0.lock().unwrap().budget_bytes = budget;
+        cache
+    }
+
+    fn total_bytes(cache: &PageCache) -> usize {
+        cache.0.lock().unwrap().total_bytes
+    }
+
+    #[test]
+    fn evicts_least_recently_used_pages_over_budget() {
+        let cache = cache_with_budget(100);
+        cache.insert(key("1"), Arc::new(vec![0; 40]));
+        cache.insert(key("2"), Arc::new(vec![0; 40]));
+        // 访问 1 后，2 成为最久未使用的页面
+        assert!(cache.get(&key("1")).is_some());
+        cache.insert(key("3"), Arc::new(vec![0; 40]));
+
+        assert!(cache.contains(&key("1")));
+        assert!(!cache.contains(&key("2")));
+        assert!(cache.contains(&key("3")));
+        assert_eq!(total_bytes(&cache), 80);
+    }
+
+    #[test]
+    fn replacing_a_page_updates_the_byte_count() {
+        let cache = cache_with_budget(100);
+        cache.insert(key("1"), Arc::new(vec![0; 60]));
+        cache.insert(key("1"), Arc::new(vec![0; 30]));
+        assert_eq!(total_bytes(&cache), 30);
+    }
+
+    #[test]
+    fn skips_pages_larger_than_the_budget() {
+        let cache = cache_with_budget(100);
+        cache.insert(key("1"), Arc::new(vec![0; 40]));
+        cache.insert(key("big"), Arc::new(vec![0; 101]));
+
+        // 过大的页面不缓存，也不会挤掉已有页面
+        assert!(!cache.contains(&key("big")));
+        assert!(cache.contains(&key("1")));
+        assert_eq!(total_bytes(&cache), 40);
+    }
+
+    #[test]
+    fn zero_budget_disables_and_clears_the_cache() {
+        let cache = cache_with_budget(100);
+        cache.insert(key("1"), Arc::new(vec![0; 40]));
+        cache.set_budget_mb(0);
+        assert!(!cache.contains(&key("1")));
+
+        cache.insert(key("2"), Arc::new(vec![0; 1]));
+        assert!(!cache.contains(&key("2")));
+        assert_eq!(total_bytes(&cache), 0);
+    }
+}
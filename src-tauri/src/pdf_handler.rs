@@ -0,0 +1,200 @@
+use image::codecs::jpeg::JpegEncoder;
+use pdfium_render::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::comic_source::{ComicSource, PageInfo};
+use crate::error::ComicError;
+use crate::file_system::ArchiveKind;
+
+/// 默认渲染宽度（像素）
+const DEFAULT_RENDER_WIDTH: u32 = 1600;
+
+/// 渲染结果的 JPEG 质量
+const JPEG_QUALITY: u8 = 90;
+
+/// PDF 标准分辨率：1 point = 1/72 英寸
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// 打包资源中 pdfium 动态库所在的子目录（见 tauri.conf.json 的 bundle.resources）
+pub const PDFIUM_RESOURCE_DIR: &str = "pdfium";
+
+/// 全局 Pdfium 实例，首次使用时加载
+static PDFIUM: OnceLock<Result<Pdfium, ComicError>> = OnceLock::new();
+
+/// 随程序打包的 pdfium 所在目录，启动时设置
+static PDFIUM_LIBRARY_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// 设置随程序打包的 pdfium 动态库所在目录（应在首次打开 PDF 之前调用）
+pub fn set_pdfium_library_dir(dir: PathBuf) {
+    let _ = PDFIUM_LIBRARY_DIR.set(dir);
+}
+
+/// 获取 Pdfium 实例：依次尝试资源目录中打包的动态库、程序目录，最后回退到系统库
+fn pdfium() -> Result<&'static Pdfium, ComicError> {
+    PDFIUM
+        .get_or_init(|| {
+            let exe_dir = std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(Path::to_path_buf));
+            let candidates = PDFIUM_LIBRARY_DIR.get().cloned().into_iter().chain(exe_dir);
+
+            let bindings = candidates
+                .map(|dir| Pdfium::pdfium_platform_library_name_at_path(&dir))
+                .find_map(|library_path| Pdfium::bind_to_library(library_path).ok())
+                .map(Ok)
+                .unwrap_or_else(Pdfium::bind_to_system_library)
+                .map_err(|e| ComicError::Pdf {
+                    path: None,
+                    source: e.to_string(),
+                })?;
+
+            Ok(Pdfium::new(bindings))
+        })
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+/// 打开 PDF 文档
+fn load_document(pdf_path: &str) -> Result<PdfDocument<'static>, ComicError> {
+    if !Path::new(pdf_path).exists() {
+        return Err(ComicError::not_found(pdf_path));
+    }
+
+    pdfium()
+        .map_err(|e| e.clone().with_path(pdf_path))?
+        .load_pdf_from_file(pdf_path, None)
+        .map_err(|e| ComicError::pdf(pdf_path, e))
+}
+
+/// 获取 PDF 页数
+pub fn get_pdf_page_count(pdf_path: &str) -> Result<u32, ComicError> {
+    let document = load_document(pdf_path)?;
+    Ok(document.pages().len() as u32)
+}
+
+/// 将已打开文档中的页面渲染为 JPEG，target_width 优先，其次按 dpi 换算，都未指定时使用默认宽度
+fn render_document_page(
+    document: &PdfDocument,
+    pdf_path: &str,
+    page_index: usize,
+    target_width: Option<u32>,
+    dpi: Option<f32>,
+) -> Result<Vec<u8>, ComicError> {
+    let page = document
+        .pages()
+        .get(page_index as PdfPageIndex)
+        .map_err(|_| ComicError::PageOutOfRange {
+            path: pdf_path.to_string(),
+            index: page_index,
+        })?;
+
+    let width = match (target_width, dpi) {
+        (Some(width), _) => width,
+        (None, Some(dpi)) => (page.width().value * dpi / POINTS_PER_INCH).round() as u32,
+        (None, None) => DEFAULT_RENDER_WIDTH,
+    };
+
+    let config = PdfRenderConfig::new().set_target_width(width.max(1) as Pixels);
+    let bitmap = page
+        .render_with_config(&config)
+        .map_err(|e| ComicError::pdf(pdf_path, e))?;
+
+    // JPEG 不支持透明通道，先转换为 RGB
+    let rgb = bitmap.as_image().to_rgb8();
+
+    let mut buffer = Vec::new();
+    JpegEncoder::new_with_quality(&mut buffer, JPEG_QUALITY)
+        .encode_image(&rgb)
+        .map_err(ComicError::encode)?;
+
+    Ok(buffer)
+}
+
+/// PDF 漫画来源：文档打开后常驻，每页按需渲染为位图
+pub struct PdfSource {
+    path: String,
+    document: PdfDocument<'static>,
+    pages: Vec<PageInfo>,
+    /// 各页面尺寸（point）
+    page_sizes: Vec<(f32, f32)>,
+}
+
+impl PdfSource {
+    /// 打开 PDF 文件并读取页面尺寸
+    pub fn open(pdf_path: &str) -> Result<Self, ComicError> {
+        let document = load_document(pdf_path)?;
+
+        let page_sizes: Vec<(f32, f32)> = document
+            .pages()
+            .page_sizes()
+            .map_err(|e| ComicError::pdf(pdf_path, e))?
+            .iter()
+            .map(|rect| (rect.width().value, rect.height().value))
+            .collect();
+
+        // 渲染结果为 JPEG，页面名带上扩展名以便前端识别 MIME 类型
+        let pages = (0..page_sizes.len())
+            .map(|index| {
+                let name = format!("{:04}.jpg", index + 1);
+                PageInfo {
+                    index,
+                    name: name.clone(),
+                    path: name,
+                    size: 0,
+                    page_type: None,
+                    double_page: false,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            path: pdf_path.to_string(),
+            document,
+            pages,
+            page_sizes,
+        })
+    }
+}
+
+impl ComicSource for PdfSource {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        Some(ArchiveKind::Pdf)
+    }
+
+    fn pages(&self) -> &[PageInfo] {
+        &self.pages
+    }
+
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError> {
+        render_document_page(&self.document, &self.path, page.index, None, None)
+    }
+
+    fn render_page(
+        &mut self,
+        page: &PageInfo,
+        target_width: Option<u32>,
+        dpi: Option<f32>,
+    ) -> Option<Result<Vec<u8>, ComicError>> {
+        Some(render_document_page(&self.document, &self.path, page.index, target_width, dpi))
+    }
+
+    fn page_dimensions(&mut self, page: &PageInfo) -> Result<(u32, u32), ComicError> {
+        // 按默认渲染宽度换算，与 read_page 输出的位图尺寸一致
+        let (width, height) = self
+            .page_sizes
+            .get(page.index)
+            .copied()
+            .ok_or_else(|| ComicError::PageOutOfRange {
+                path: self.path.clone(),
+                index: page.index,
+            })?;
+
+        let scale = DEFAULT_RENDER_WIDTH as f32 / width.max(1.0);
+        Ok((DEFAULT_RENDER_WIDTH, (height * scale).round() as u32))
+    }
+}
@@ -0,0 +1,265 @@
+use std::path::{Component, Path};
+
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+use crate::comic_source::{get_page_info, read_page_entry, SourceCache};
+use crate::crop::read_page_cropped;
+use crate::epub_handler::percent_decode;
+use crate::error::ComicError;
+use crate::file_system::page_stamp;
+use crate::i18n::{tr, Msg};
+use crate::image_handler::get_mime_type;
+use crate::library::{get_library_entry, LibraryDb};
+use crate::spread::{read_page_half, PageHalf};
+use crate::storage::{get_opened_directories, AppDataCache};
+
+/// 自定义协议名
+/// 前端通过 `comic://localhost/<编码后的漫画路径>/<页码>` 直接在 <img> 中加载页面，
+/// 附加 `?crop=1` 时返回去掉边框后的页面，附加 `?half=left|right` 时返回跨页的半边（优先于裁剪）；
+/// Windows 下对应 `http://comic.localhost/...`（由 convertFileSrc 生成）。
+/// 只响应已打开目录下或漫画库中的漫画，其他路径返回 403
+pub const COMIC_PROTOCOL: &str = "comic";
+
+/// 请求的字节范围（闭区间）
+struct ByteRange {
+    start: usize,
+    end: usize,
+}
+
+/// 解析 URL 路径：/<编码后的漫画路径>/<页码>
+fn parse_request_path(path: &str) -> Option<(String, usize)> {
+    let (source, page) = path.trim_start_matches('/').rsplit_once('/')?;
+    let page = page.parse().ok()?;
+    let source = percent_decode(source);
+    if source.is_empty() {
+        return None;
+    }
+    Some((source, page))
+}
+
+/// 路径是否为不含 `..` 的绝对路径
+fn is_plain_absolute(path: &Path) -> bool {
+    path.is_absolute() && !path.components().any(|c| matches!(c, Component::ParentDir))
+}
+
+/// 漫画是否允许通过协议访问：位于已打开的目录下，或是漫画库中的漫画
+fn is_allowed_comic(app: &AppHandle, comic_path: &str) -> bool {
+    let path = Path::new(comic_path);
+    if !is_plain_absolute(path) {
+        return false;
+    }
+
+    let under_opened = get_opened_directories(app, &app.state::<AppDataCache>())
+        .map(|directories| directories.iter().any(|d| path.starts_with(&d.path)))
+        .unwrap_or(false);
+    under_opened
+        || get_library_entry(app, &app.state::<LibraryDb>(), comic_path)
+            .map(|entry| entry.is_some_and(|e| e.is_comic))
+            .unwrap_or(false)
+}
+
+/// 读取查询参数
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// 页面的 ETag：页面所在文件（文件夹漫画为图片本身）的修改时间 + 大小 + 页码（+ 处理方式）
+fn page_etag(comic_path: &str, page_index: usize, variant: &str, cache: &SourceCache) -> Option<String> {
+    let page = get_page_info(comic_path, page_index, cache).ok()?;
+    let stamp = page_stamp(comic_path, &page.path)?;
+    Some(format!("\"{:x}-{:x}-{}{}\"", stamp.mtime, stamp.size, page_index, variant))
+}
+
+/// 解析 Range 请求头，只支持单个范围；多个范围时返回 None，按完整内容响应
+fn parse_range(value: &str, total: usize) -> Option<Result<ByteRange, ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+
+    let range = match (start.trim(), end.trim()) {
+        // bytes=-N：最后 N 个字节
+        ("", suffix) => {
+            let suffix: usize = suffix.parse().ok()?;
+            if suffix == 0 || total == 0 {
+                return Some(Err(()));
+            }
+            ByteRange {
+                start: total.saturating_sub(suffix),
+                end: total - 1,
+            }
+        }
+        // bytes=N- 或 bytes=N-M
+        (start, end) => {
+            let start: usize = start.parse().ok()?;
+            let end: usize = if end.is_empty() { usize::MAX } else { end.parse().ok()? };
+            if start >= total || end < start {
+                return Some(Err(()));
+            }
+            ByteRange {
+                start,
+                end: end.min(total - 1),
+            }
+        }
+    };
+
+    Some(Ok(range))
+}
+
+/// 构造错误响应
+fn error_response(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(message.into_bytes())
+        .unwrap()
+}
+
+/// 处理 comic:// 请求：返回页面数据，支持 ETag 协商缓存和 Range 请求
+pub fn handle_comic_request(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let Some((comic_path, page_index)) = parse_request_path(request.uri().path()) else {
+        return error_response(StatusCode::BAD_REQUEST, tr(Msg::InvalidPageUrl, &[request.uri()]));
+    };
+    if !is_allowed_comic(app, &comic_path) {
+        return error_response(StatusCode::FORBIDDEN, tr(Msg::PathNotAllowed, &[&comic_path]));
+    }
+
+    let query = request.uri().query();
+    let half = query_param(query, "half").and_then(PageHalf::parse);
+    let crop = matches!(query_param(query, "crop"), Some("1" | "true"));
+    let variant = match (half, crop) {
+        (Some(PageHalf::Left), _) => "-left",
+        (Some(PageHalf::Right), _) => "-right",
+        (None, true) => "-crop",
+        (None, false) => "",
+    };
+    let cache = app.state::<SourceCache>();
+    let etag = page_etag(&comic_path, page_index, variant, &cache);
+
+    // 页面未变化时直接返回 304，不读取数据
+    if let (Some(etag), Some(if_none_match)) = (&etag, request.headers().get(header::IF_NONE_MATCH)) {
+        if if_none_match.to_str().map(|v| v.split(',').any(|t| t.trim() == etag)).unwrap_or(false) {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(header::ETAG, etag.as_str())
+                .body(Vec::new())
+                .unwrap();
+        }
+    }
+
+    let result = if let Some(half) = half {
+        read_page_half(&cache, &comic_path, page_index, half)
+    } else if crop {
+        read_page_cropped(app, &app.state::<LibraryDb>(), &cache, &comic_path, page_index)
+            .map(|(_, buffer, mime)| (buffer, mime))
+    } else {
+        read_page_entry(&comic_path, page_index, &cache).map(|(page, buffer)| (buffer, get_mime_type(&page.name)))
+    };
+    let (buffer, mime) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            let status = match e {
+                ComicError::NotFound { .. } | ComicError::EntryNotFound { .. } | ComicError::PageOutOfRange { .. } => {
+                    StatusCode::NOT_FOUND
+                }
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            return error_response(status, e.to_string());
+        }
+    };
+
+    let total = buffer.len();
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, "no-cache");
+    if let Some(etag) = &etag {
+        builder = builder.header(header::ETAG, etag.as_str());
+    }
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_range(v, total));
+
+    match range {
+        Some(Ok(range)) => {
+            let body = buffer[range.start..=range.end].to_vec();
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end, total))
+                .header(header::CONTENT_LENGTH, body.len())
+                .body(body)
+                .unwrap()
+        }
+        Some(Err(())) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", total))
+            .body(Vec::new())
+            .unwrap(),
+        None => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, total)
+            .body(buffer)
+            .unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(value: &str, total: usize) -> Option<Result<(usize, usize), ()>> {
+        parse_range(value, total).map(|r| r.map(|r| (r.start, r.end)))
+    }
+
+    #[test]
+    fn parse_range_open_ended() {
+        assert_eq!(range("bytes=0-", 1000), Some(Ok((0, 999))));
+        assert_eq!(range("bytes=200-", 1000), Some(Ok((200, 999))));
+    }
+
+    #[test]
+    fn parse_range_bounded_is_clamped_to_length() {
+        assert_eq!(range("bytes=10-19", 1000), Some(Ok((10, 19))));
+        assert_eq!(range("bytes=900-5000", 1000), Some(Ok((900, 999))));
+    }
+
+    #[test]
+    fn parse_range_suffix() {
+        assert_eq!(range("bytes=-500", 1000), Some(Ok((500, 999))));
+        assert_eq!(range("bytes=-5000", 1000), Some(Ok((0, 999))));
+    }
+
+    #[test]
+    fn parse_range_unsatisfiable() {
+        assert_eq!(range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(range("bytes=2000-3000", 1000), Some(Err(())));
+        assert_eq!(range("bytes=20-10", 1000), Some(Err(())));
+        assert_eq!(range("bytes=-0", 1000), Some(Err(())));
+        assert_eq!(range("bytes=-10", 0), Some(Err(())));
+    }
+
+    #[test]
+    fn plain_absolute_rejects_relative_and_parent_components() {
+        let root = std::env::temp_dir();
+        assert!(is_plain_absolute(&root.join("comics").join("a.cbz")));
+        assert!(!is_plain_absolute(&root.join("comics").join("..").join("secret")));
+        assert!(!is_plain_absolute(Path::new("comics/a.cbz")));
+    }
+
+    #[test]
+    fn parse_range_ignores_multi_range_and_malformed_headers() {
+        assert_eq!(range("bytes=0-10,20-30", 1000), None);
+        assert_eq!(range("items=0-10", 1000), None);
+        assert_eq!(range("bytes=abc-", 1000), None);
+        assert_eq!(range("bytes=10", 1000), None);
+    }
+}
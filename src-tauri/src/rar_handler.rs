@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::path::Path;
+use unrar::{Archive, CursorBeforeHeader, OpenArchive, Process};
+
+use crate::comic_source::{build_page_list, page_positions, ComicSource, PageInfo};
+use crate::error::ComicError;
+use crate::file_system::ArchiveKind;
+use crate::image_handler::get_image_dimensions_from_bytes;
+
+/// 顺序解压的位置：停在某个文件头之前的已打开压缩包
+struct RarCursor {
+    archive: OpenArchive<Process, CursorBeforeHeader>,
+    /// 下一个文件头的序号
+    position: usize,
+}
+
+// SAFETY: unrar 的句柄没有线程亲和性，只要求不被并发使用；
+// 游标归 RarSource 独占，而 RarSource 由句柄池每次只借给一个线程
+unsafe impl Send for RarCursor {}
+
+/// RAR 漫画来源：unrar 只能顺序解压，打开时记录各条目的序号，
+/// 读取后保留解压位置，按顺序翻页时从上次的位置继续，只有往回翻才重新打开（固实压缩包尤其明显）
+pub struct RarSource {
+    path: String,
+    pages: Vec<PageInfo>,
+    /// 条目路径 -> 文件头序号（含目录）
+    positions: HashMap<String, usize>,
+    cursor: Option<RarCursor>,
+}
+
+impl RarSource {
+    /// 扫描 RAR 文件头，读取图片列表
+    pub fn open(rar_path: &str) -> Result<Self, ComicError> {
+        if !Path::new(rar_path).exists() {
+            return Err(ComicError::not_found(rar_path));
+        }
+
+        let archive = Archive::new(rar_path)
+            .open_for_listing()
+            .map_err(|e| ComicError::corrupt(rar_path, e))?;
+
+        let mut entries = Vec::new();
+        let mut positions = HashMap::new();
+
+        for (position, entry) in archive.enumerate() {
+            let header = entry.map_err(|e| ComicError::corrupt(rar_path, e))?;
+
+            // 跳过目录
+            if header.is_directory() {
+                continue;
+            }
+            let filename = header.filename.to_string_lossy().to_string();
+            positions.insert(filename.clone(), position);
+            entries.push((filename, header.unpacked_size));
+        }
+
+        Ok(Self {
+            path: rar_path.to_string(),
+            pages: build_page_list(entries),
+            positions,
+            cursor: None,
+        })
+    }
+}
+
+impl RarSource {
+    /// 从游标（None 时从头）开始顺序遍历文件头，跳过其他条目直到目标图片，读取后保留游标
+    fn read_from(&mut self, cursor: Option<RarCursor>, entry: &str) -> Result<Option<Vec<u8>>, ComicError> {
+        let RarCursor { mut archive, mut position } = match cursor {
+            Some(cursor) => cursor,
+            None => RarCursor {
+                archive: Archive::new(&self.path)
+                    .open_for_processing()
+                    .map_err(|e| ComicError::corrupt(&self.path, e))?,
+                position: 0,
+            },
+        };
+
+        while let Some(header) = archive
+            .read_header()
+            .map_err(|e| ComicError::corrupt(&self.path, e))?
+        {
+            if header.entry().filename.to_string_lossy() == entry {
+                let (buffer, next) = header
+                    .read()
+                    .map_err(|e| ComicError::corrupt(&self.path, e))?;
+                self.cursor = Some(RarCursor {
+                    archive: next,
+                    position: position + 1,
+                });
+                return Ok(Some(buffer));
+            }
+
+            archive = header
+                .skip()
+                .map_err(|e| ComicError::corrupt(&self.path, e))?;
+            position += 1;
+        }
+
+        Ok(None)
+    }
+}
+
+impl ComicSource for RarSource {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        Some(ArchiveKind::Rar)
+    }
+
+    fn pages(&self) -> &[PageInfo] {
+        &self.pages
+    }
+
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError> {
+        let target = *self
+            .positions
+            .get(&page.path)
+            .ok_or_else(|| ComicError::entry_not_found(&self.path, &page.path))?;
+
+        // 目标在上次位置之后时继续解压，否则从头打开；出错时丢弃游标，下次重新打开
+        let resumed = self.cursor.take().filter(|cursor| cursor.position <= target);
+        let from_start = resumed.is_none();
+        if let Some(buffer) = self.read_from(resumed, &page.path)? {
+            return Ok(buffer);
+        }
+        // 序号与实际文件头不一致（如分卷压缩包）时从头再找一遍
+        if !from_start {
+            if let Some(buffer) = self.read_from(None, &page.path)? {
+                return Ok(buffer);
+            }
+        }
+
+        Err(ComicError::entry_not_found(&self.path, &page.path))
+    }
+
+    fn all_page_dimensions(&mut self) -> Vec<Option<(u32, u32)>> {
+        let mut dimensions = vec![None; self.pages.len()];
+        let positions = page_positions(&self.pages);
+
+        let Ok(mut archive) = Archive::new(&self.path).open_for_processing() else {
+            return dimensions;
+        };
+
+        // 单次遍历：unrar 只能整条解压，但避免了每页都从头遍历
+        while let Ok(Some(header)) = archive.read_header() {
+            let filename = header.entry().filename.to_string_lossy().to_string();
+            let next = match positions.get(filename.as_str()) {
+                Some(&position) => header.read().map(|(buffer, next)| {
+                    dimensions[position] = get_image_dimensions_from_bytes(buffer).ok();
+                    next
+                }),
+                None => header.skip(),
+            };
+
+            match next {
+                Ok(next) => archive = next,
+                Err(_) => break,
+            }
+        }
+
+        dimensions
+    }
+}
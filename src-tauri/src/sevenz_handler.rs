@@ -0,0 +1,98 @@
+use sevenz_rust::{Password, SevenZReader};
+use std::fs::File;
+use std::path::Path;
+
+use crate::comic_source::{build_page_list, page_positions, ComicSource, PageInfo};
+use crate::error::ComicError;
+use crate::file_system::ArchiveKind;
+use crate::image_handler::get_image_dimensions_from_reader;
+
+/// 7z 漫画来源：保留打开的 7z 句柄，避免重复解析文件头
+pub struct SevenZSource {
+    path: String,
+    reader: SevenZReader<File>,
+    pages: Vec<PageInfo>,
+}
+
+impl SevenZSource {
+    /// 打开 7z 文件并读取图片列表
+    pub fn open(archive_path: &str) -> Result<Self, ComicError> {
+        let path = Path::new(archive_path);
+
+        if !path.exists() {
+            return Err(ComicError::not_found(archive_path));
+        }
+
+        let reader = SevenZReader::open(path, Password::empty())
+            .map_err(|e| ComicError::corrupt(archive_path, e))?;
+
+        let entries = reader
+            .archive()
+            .files
+            .iter()
+            .filter(|entry| !entry.is_directory())
+            .map(|entry| (entry.name().to_string(), entry.size()));
+        let pages = build_page_list(entries);
+
+        Ok(Self {
+            path: archive_path.to_string(),
+            reader,
+            pages,
+        })
+    }
+}
+
+impl ComicSource for SevenZSource {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        Some(ArchiveKind::SevenZip)
+    }
+
+    fn pages(&self) -> &[PageInfo] {
+        &self.pages
+    }
+
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError> {
+        let mut buffer: Option<Vec<u8>> = None;
+
+        self.reader
+            .for_each_entries(|entry, data| {
+                if buffer.is_some() {
+                    return Ok(false);
+                }
+
+                if entry.name() == page.path {
+                    let mut content = Vec::with_capacity(entry.size() as usize);
+                    data.read_to_end(&mut content)?;
+                    buffer = Some(content);
+                    return Ok(false);
+                }
+
+                // 固实压缩块只能顺序解压，跳过的条目也必须读完
+                std::io::copy(data, &mut std::io::sink())?;
+                Ok(true)
+            })
+            .map_err(|e| ComicError::corrupt(&self.path, e))?;
+
+        buffer.ok_or_else(|| ComicError::entry_not_found(&self.path, &page.path))
+    }
+
+    fn all_page_dimensions(&mut self) -> Vec<Option<(u32, u32)>> {
+        let mut dimensions = vec![None; self.pages.len()];
+        let positions = page_positions(&self.pages);
+
+        // 单次遍历，每个图片只解析文件头，其余数据仍需读完才能继续解压
+        let _ = self.reader.for_each_entries(|entry, data| {
+            if let Some(&position) = positions.get(entry.name()) {
+                dimensions[position] = get_image_dimensions_from_reader(data).ok();
+            }
+            std::io::copy(data, &mut std::io::sink())?;
+            Ok(true)
+        });
+
+        dimensions
+    }
+}
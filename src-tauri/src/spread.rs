@@ -0,0 +1,389 @@
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::AppHandle;
+
+use crate::comic_info::ComicPageType;
+use crate::comic_source::{get_comic_metadata, get_page_info, get_pages, read_page_entry, PageInfo, SourceCache};
+use crate::crop::{crop_image, CropBox};
+use crate::error::ComicError;
+use crate::i18n::{tr, Msg};
+use crate::image_handler::get_image_dimensions_from_bytes;
+use crate::library::{get_page_dimensions_cached, get_page_gutter_cached, LibraryDb};
+use crate::page_cache::PageKey;
+
+/// 宽高比不小于该值直接视为跨页
+const SPREAD_ASPECT: f32 = 1.3;
+
+/// 宽高比在此与 SPREAD_ASPECT 之间时，还需检测中缝
+const GUTTER_MIN_ASPECT: f32 = 1.05;
+
+/// 中缝检测范围：图片中间该比例宽度内的列
+const GUTTER_BAND_RATIO: f32 = 0.04;
+
+/// 中缝列中与中位亮度相差不超过该值的像素视为一致
+const GUTTER_TOLERANCE: u8 = 24;
+
+/// 中缝列中一致像素的最低比例
+const GUTTER_UNIFORM_RATIO: f32 = 0.9;
+
+/// 跨页拆分方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpreadSplit {
+    /// 不拆分
+    #[default]
+    Off,
+    /// 左右阅读：先左半页再右半页
+    Ltr,
+    /// 右左阅读（日漫）：先右半页再左半页
+    Rtl,
+}
+
+/// 跨页的半边
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageHalf {
+    Left,
+    Right,
+}
+
+impl PageHalf {
+    /// 解析 URL 查询参数中的半边
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "left" => Some(PageHalf::Left),
+            "right" => Some(PageHalf::Right),
+            _ => None,
+        }
+    }
+
+    /// 半边在整页中的区域
+    fn crop_box(self, width: u32, height: u32) -> CropBox {
+        let left_width = width / 2;
+        match self {
+            PageHalf::Left => CropBox {
+                x: 0,
+                y: 0,
+                width: left_width,
+                height,
+            },
+            PageHalf::Right => CropBox {
+                x: left_width,
+                y: 0,
+                width: width - left_width,
+                height,
+            },
+        }
+    }
+}
+
+/// 页面列表中的虚拟页：普通页面对应整页，拆分后的跨页对应其中半边
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualPage {
+    #[serde(rename = "pageIndex")]
+    pub page_index: usize,
+    pub half: Option<PageHalf>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// 书本模式中的一个展开页：单页或左右两页
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spread {
+    /// 页码，按显示顺序从左到右（右左阅读时第一页在右侧）
+    pub pages: Vec<usize>,
+}
+
+/// 中间区域是否存在中缝：某一列的像素几乎都接近同一亮度（装订处的留白或接缝）
+fn has_gutter(gray: &GrayImage) -> bool {
+    let (w, h) = gray.dimensions();
+    if w < 2 || h == 0 {
+        return false;
+    }
+
+    let band = ((w as f32 * GUTTER_BAND_RATIO) as u32).max(1);
+    let start = (w / 2).saturating_sub(band / 2);
+    let end = (start + band).min(w);
+
+    (start..end).any(|x| {
+        let mut column: Vec<u8> = (0..h).map(|y| gray.get_pixel(x, y)[0]).collect();
+        column.sort_unstable();
+        let median = column[column.len() / 2];
+        let uniform = column.iter().filter(|p| p.abs_diff(median) <= GUTTER_TOLERANCE).count();
+        uniform as f32 >= h as f32 * GUTTER_UNIFORM_RATIO
+    })
+}
+
+/// 检测页面图片中间是否有中缝
+pub fn detect_gutter_from_bytes(buffer: &[u8]) -> Result<bool, ComicError> {
+    let img = image::load_from_memory(buffer).map_err(ComicError::decode)?;
+    Ok(has_gutter(&img.to_luma8()))
+}
+
+/// 判断页面是否为跨页：足够宽的直接判定，略宽的再检测中缝（检测结果记录在漫画库中）
+fn is_spread(
+    app: &AppHandle,
+    db: &LibraryDb,
+    sources: &SourceCache,
+    comic_path: &str,
+    page_index: usize,
+    dimensions: Option<(u32, u32)>,
+) -> bool {
+    let Some((width, height)) = dimensions else {
+        return false;
+    };
+    if height == 0 {
+        return false;
+    }
+
+    let aspect = width as f32 / height as f32;
+    if aspect >= SPREAD_ASPECT {
+        return true;
+    }
+    if aspect < GUTTER_MIN_ASPECT {
+        return false;
+    }
+
+    get_page_gutter_cached(app, db, sources, comic_path, page_index).unwrap_or(false)
+}
+
+/// 生成虚拟页列表：按拆分方式把跨页拆成两个半页，文件夹和各种压缩包统一处理
+pub fn get_virtual_pages(
+    app: &AppHandle,
+    db: &LibraryDb,
+    sources: &SourceCache,
+    comic_path: &str,
+    split: SpreadSplit,
+) -> Result<Vec<VirtualPage>, ComicError> {
+    let dimensions = get_page_dimensions_cached(app, db, sources, comic_path)?;
+    let mut pages = Vec::with_capacity(dimensions.len());
+
+    for (page_index, size) in dimensions.into_iter().enumerate() {
+        let whole = VirtualPage {
+            page_index,
+            half: None,
+            width: size.map(|(w, _)| w),
+            height: size.map(|(_, h)| h),
+        };
+
+        if split == SpreadSplit::Off || !is_spread(app, db, sources, comic_path, page_index, size) {
+            pages.push(whole);
+            continue;
+        }
+
+        let (width, height) = size.unwrap_or_default();
+        let halves = match split {
+            SpreadSplit::Rtl => [PageHalf::Right, PageHalf::Left],
+            _ => [PageHalf::Left, PageHalf::Right],
+        };
+        for half in halves {
+            let area = half.crop_box(width, height);
+            pages.push(VirtualPage {
+                page_index,
+                half: Some(half),
+                width: Some(area.width),
+                height: Some(area.height),
+            });
+        }
+    }
+
+    Ok(pages)
+}
+
+/// 读取跨页的半边，返回数据和 MIME 类型；结果缓存在页面缓存中
+pub fn read_page_half(
+    sources: &SourceCache,
+    comic_path: &str,
+    page_index: usize,
+    half: PageHalf,
+) -> Result<(Vec<u8>, &'static str), ComicError> {
+    let page = get_page_info(comic_path, page_index, sources)?;
+    let key = PageKey::new(comic_path, &page.path).with_variant(format!("half:{:?}", half));
+
+    if let Some(buffer) = sources.pages.get(&key) {
+        let mime = if buffer.starts_with(b"\x89PNG") { "image/png" } else { "image/jpeg" };
+        return Ok((buffer.to_vec(), mime));
+    }
+
+    let (_, original) = read_page_entry(comic_path, page_index, sources)?;
+    let (width, height) = get_image_dimensions_from_bytes(original.clone())?;
+    let area = half.crop_box(width, height);
+
+    // 半边区域不可能覆盖整页（宽度至少为 2 时），crop_image 总会返回裁剪结果
+    let (buffer, mime) = crop_image(&original, area)?.ok_or_else(|| ComicError::ImageDecode {
+        path: Some(comic_path.to_string()),
+        source: tr(Msg::SplitPageFailed, &[&page.name]),
+    })?;
+    sources.pages.insert(key, Arc::new(buffer.clone()));
+    Ok((buffer, mime))
+}
+
+/// 页面是否单独成页：封面、封底，以及横向的跨页
+fn stands_alone(page: &PageInfo, size: Option<(u32, u32)>) -> bool {
+    if matches!(page.page_type, Some(ComicPageType::FrontCover | ComicPageType::BackCover)) || page.double_page {
+        return true;
+    }
+    size.map(|(w, h)| h > 0 && w as f32 / h as f32 >= SPREAD_ASPECT).unwrap_or(false)
+}
+
+/// 把页面分组为展开页：第一页（封面）和横向页面单独显示，其余相邻页面两两配对；
+/// offset 为 true 时在封面后再多放一个单页，使配对整体错开一页
+pub fn plan_spreads(
+    pages: &[PageInfo],
+    dimensions: &[Option<(u32, u32)>],
+    offset: bool,
+    right_to_left: bool,
+) -> Vec<Spread> {
+    let alone = |i: usize| i == 0 || stands_alone(&pages[i], dimensions.get(i).copied().flatten());
+    let mut spreads = Vec::new();
+    let mut i = 0;
+
+    while i < pages.len() {
+        let single = (offset && i == 1) || alone(i) || i + 1 >= pages.len() || alone(i + 1);
+        if single {
+            spreads.push(Spread { pages: vec![i] });
+            i += 1;
+            continue;
+        }
+
+        let pair = if right_to_left { vec![i + 1, i] } else { vec![i, i + 1] };
+        spreads.push(Spread { pages: pair });
+        i += 2;
+    }
+
+    spreads
+}
+
+/// 生成漫画的书本模式分组；right_to_left 省略时按 ComicInfo 的 Manga 字段决定
+pub fn get_spread_plan(
+    app: &AppHandle,
+    db: &LibraryDb,
+    sources: &SourceCache,
+    comic_path: &str,
+    offset: bool,
+    right_to_left: Option<bool>,
+) -> Result<Vec<Spread>, ComicError> {
+    let pages = get_pages(comic_path, sources)?;
+    let dimensions = get_page_dimensions_cached(app, db, sources, comic_path)?;
+    let right_to_left = match right_to_left {
+        Some(rtl) => rtl,
+        None => get_comic_metadata(comic_path, sources)?.right_to_left,
+    };
+    Ok(plan_spreads(&pages, &dimensions, offset, right_to_left))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORTRAIT: Option<(u32, u32)> = Some((800, 1200));
+    const WIDE: Option<(u32, u32)> = Some((1600, 1200));
+
+    fn pages(count: usize) -> Vec<PageInfo> {
+        (0..count)
+            .map(|index| PageInfo {
+                index,
+                name: format!("{:03}.jpg", index),
+                path: format!("{:03}.jpg", index),
+                size: 0,
+                page_type: None,
+                double_page: false,
+            })
+            .collect()
+    }
+
+    fn plan(pages: &[PageInfo], dimensions: &[Option<(u32, u32)>], offset: bool, rtl: bool) -> Vec<Vec<usize>> {
+        plan_spreads(pages, dimensions, offset, rtl)
+            .into_iter()
+            .map(|spread| spread.pages)
+            .collect()
+    }
+
+    #[test]
+    fn cover_stands_alone_and_the_rest_pair_up() {
+        let pages = pages(6);
+        assert_eq!(
+            plan(&pages, &[PORTRAIT; 6], false, false),
+            vec![vec![0], vec![1, 2], vec![3, 4], vec![5]]
+        );
+    }
+
+    #[test]
+    fn offset_shifts_pairs_by_one_page() {
+        let pages = pages(6);
+        assert_eq!(
+            plan(&pages, &[PORTRAIT; 6], true, false),
+            vec![vec![0], vec![1], vec![2, 3], vec![4, 5]]
+        );
+    }
+
+    #[test]
+    fn right_to_left_puts_the_later_page_on_the_left() {
+        let pages = pages(5);
+        assert_eq!(
+            plan(&pages, &[PORTRAIT; 5], false, true),
+            vec![vec![0], vec![2, 1], vec![4, 3]]
+        );
+    }
+
+    #[test]
+    fn wide_pages_and_declared_double_pages_stand_alone() {
+        let mut pages = pages(7);
+        pages[5].double_page = true;
+        let dimensions = [PORTRAIT, PORTRAIT, WIDE, PORTRAIT, PORTRAIT, PORTRAIT, PORTRAIT];
+        assert_eq!(
+            plan(&pages, &dimensions, false, false),
+            vec![vec![0], vec![1], vec![2], vec![3, 4], vec![5], vec![6]]
+        );
+    }
+
+    #[test]
+    fn back_cover_stands_alone() {
+        let mut pages = pages(4);
+        pages[3].page_type = Some(ComicPageType::BackCover);
+        assert_eq!(
+            plan(&pages, &[PORTRAIT; 4], false, false),
+            vec![vec![0], vec![1, 2], vec![3]]
+        );
+    }
+
+    /// 600x400 的页面，像素亮度按坐标变化，没有任何均匀的列
+    fn busy_page() -> GrayImage {
+        GrayImage::from_fn(600, 400, |x, y| image::Luma([((x * 37 + y * 91) % 256) as u8]))
+    }
+
+    /// 把第 x 列设为接近白色，其中 noisy 个像素保持原样
+    fn paint_column(gray: &mut GrayImage, x: u32, noisy: u32) {
+        for y in noisy..gray.height() {
+            gray.put_pixel(x, y, image::Luma([240 + (y % 10) as u8]));
+        }
+    }
+
+    #[test]
+    fn busy_page_has_no_gutter() {
+        assert!(!has_gutter(&busy_page()));
+    }
+
+    #[test]
+    fn uniform_column_in_the_middle_is_a_gutter() {
+        let mut gray = busy_page();
+        // 中间带宽 24 列（300 ± 12），允许 10% 的像素不均匀
+        paint_column(&mut gray, 310, 30);
+        assert!(has_gutter(&gray));
+    }
+
+    #[test]
+    fn uniform_column_outside_the_band_or_too_noisy_is_not_a_gutter() {
+        let mut gray = busy_page();
+        paint_column(&mut gray, 250, 0);
+        paint_column(&mut gray, 300, 60);
+        assert!(!has_gutter(&gray));
+    }
+
+    #[test]
+    fn degenerate_images_have_no_gutter() {
+        assert!(!has_gutter(&GrayImage::new(1, 400)));
+        assert!(!has_gutter(&GrayImage::new(600, 0)));
+    }
+}
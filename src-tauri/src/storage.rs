@@ -1,17 +1,147 @@
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
 use tauri::AppHandle;
 use tauri::Manager;
 
-/// 内存缓存，避免每次操作都读写磁盘
-pub struct AppDataCache(pub Mutex<Option<AppData>>);
+use crate::error::ComicError;
+use crate::i18n::{tr, Language, Msg};
+use crate::page_cache::DEFAULT_PAGE_CACHE_MB;
+use crate::spread::SpreadSplit;
+
+/// 设置、阅读进度、书签和打开的目录所在的数据库文件名
+const STORAGE_DB_FILE: &str = "comic_data.db";
+
+/// 数据库表结构版本（PRAGMA user_version），0 表示尚未导入 comic_data.json
+const STORAGE_SCHEMA_VERSION: u32 = 1;
+
+/// 最多保存的打开目录数
+const MAX_OPENED_DIRECTORIES: usize = 10;
+
+/// 阅读进度默认的合并写入窗口（毫秒）
+const DEFAULT_PROGRESS_SAVE_DELAY_MS: u64 = 1000;
+
+/// comic_data.json 的格式版本；没有 version 字段的旧文件视为版本 1
+pub const DATA_VERSION: u32 = 2;
+
+/// 迁移链：MIGRATIONS[i] 把版本 i + 1 的数据升级到版本 i + 2
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[migrate_v1_to_v2];
+
+/// 内存缓存，避免每次读取都查询数据库；数据库连接首次使用时打开
+///
+/// 阅读进度先放入待写入队列，由后台线程在写入窗口结束后合并写入数据库
+pub struct AppDataCache {
+    data: Mutex<Option<AppData>>,
+    conn: Mutex<Option<Connection>>,
+    progress_queue: ProgressQueue,
+    progress_delay_ms: AtomicU64,
+}
 
 impl Default for AppDataCache {
     fn default() -> Self {
-        Self(Mutex::new(None))
+        Self {
+            data: Mutex::new(None),
+            conn: Mutex::new(None),
+            progress_queue: ProgressQueue::default(),
+            progress_delay_ms: AtomicU64::new(DEFAULT_PROGRESS_SAVE_DELAY_MS),
+        }
+    }
+}
+
+#[derive(Default)]
+struct ProgressQueueState {
+    /// 待写入的进度，同一漫画只保留最新一条
+    pending: HashMap<String, ReadingProgress>,
+    /// 正在写入的一批进度，写入完成前读取仍以它为准
+    in_flight: Option<Arc<HashMap<String, ReadingProgress>>>,
+    /// 程序退出，后台线程应结束
+    stopped: bool,
+}
+
+/// 阅读进度的待写入队列
+///
+/// 同一时刻只允许一批进度在写入：后写入的批次会等待前一批完成，
+/// 保证退出时的最后一次写入一定在后台线程的写入之后，且旧进度不会覆盖新进度
+#[derive(Default)]
+struct ProgressQueue {
+    state: Mutex<ProgressQueueState>,
+    changed: Condvar,
+}
+
+impl ProgressQueue {
+    fn push(&self, progress: ReadingProgress) -> Result<(), ComicError> {
+        let mut state = self.state.lock()?;
+        state.pending.insert(progress.comic_path.clone(), progress);
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    /// 队列中尚未写入（含正在写入）的进度，同一漫画以较新的为准
+    fn pending(&self) -> Result<Vec<ReadingProgress>, ComicError> {
+        let state = self.state.lock()?;
+        let mut merged: HashMap<&str, &ReadingProgress> = HashMap::new();
+        for (path, progress) in state.in_flight.iter().flat_map(|batch| batch.iter()) {
+            merged.insert(path, progress);
+        }
+        for (path, progress) in &state.pending {
+            merged.insert(path, progress);
+        }
+        Ok(merged.into_values().cloned().collect())
+    }
+
+    /// 等待前一批写入完成，取出队列中的进度交给 write 写入；写入失败时放回队列，期间更新过的以新的为准
+    fn flush(
+        &self,
+        write: impl FnOnce(&HashMap<String, ReadingProgress>) -> Result<(), ComicError>,
+    ) -> Result<(), ComicError> {
+        let mut state = self.changed.wait_while(self.state.lock()?, |s| s.in_flight.is_some())?;
+        if state.pending.is_empty() {
+            return Ok(());
+        }
+        let batch = Arc::new(std::mem::take(&mut state.pending));
+        state.in_flight = Some(batch.clone());
+        drop(state);
+
+        let result = write(&batch);
+
+        let mut state = self.state.lock()?;
+        state.in_flight = None;
+        if result.is_err() {
+            for (path, progress) in batch.iter() {
+                state.pending.entry(path.clone()).or_insert_with(|| progress.clone());
+            }
+        }
+        self.changed.notify_all();
+        result
+    }
+
+    /// 等待队列中出现进度后，再等待一个写入窗口；返回 false 表示已停止
+    fn wait_for_batch(&self, delay: Duration) -> bool {
+        let Ok(state) = self.state.lock() else {
+            return false;
+        };
+        let Ok(state) = self.changed.wait_while(state, |s| s.pending.is_empty() && !s.stopped) else {
+            return false;
+        };
+
+        // 写入窗口内继续接收更新，停止时立即返回
+        let Ok((state, _)) = self.changed.wait_timeout_while(state, delay, |s| !s.stopped) else {
+            return false;
+        };
+        !state.stopped
+    }
+
+    fn stop(&self) -> Result<(), ComicError> {
+        self.state.lock()?.stopped = true;
+        self.changed.notify_all();
+        Ok(())
     }
 }
 
@@ -30,6 +160,9 @@ pub struct ReadingProgress {
     pub zoom_mode: Option<String>,
     #[serde(rename = "customZoom", default)]
     pub custom_zoom: Option<f64>,
+    /// 书本模式："single" | "double" | "double-offset"
+    #[serde(rename = "spreadMode", default)]
+    pub spread_mode: Option<String>,
 }
 
 fn default_zoom_mode() -> Option<String> {
@@ -69,6 +202,21 @@ pub struct Settings {
     pub custom_aspect_width: u32,
     #[serde(rename = "customAspectHeight", default = "default_aspect_height")]
     pub custom_aspect_height: u32,
+    /// 页面内存缓存预算（MB）
+    #[serde(rename = "pageCacheSizeMb", default = "default_page_cache_size_mb")]
+    pub page_cache_size_mb: u32,
+    /// 自动裁剪页面四周的纯色边框
+    #[serde(rename = "autoCrop", default)]
+    pub auto_crop: bool,
+    /// 跨页拆分方式
+    #[serde(rename = "spreadSplit", default)]
+    pub spread_split: SpreadSplit,
+    /// 阅读进度的合并写入窗口（毫秒），0 为每次立即写入
+    #[serde(rename = "progressSaveDelayMs", default = "default_progress_save_delay_ms")]
+    pub progress_save_delay_ms: u64,
+    /// 后端消息（错误、警告）的语言，None 为跟随系统
+    #[serde(default)]
+    pub language: Option<Language>,
 }
 
 fn default_reader_mode() -> String {
@@ -87,6 +235,14 @@ fn default_aspect_height() -> u32 {
     4
 }
 
+fn default_page_cache_size_mb() -> u32 {
+    DEFAULT_PAGE_CACHE_MB
+}
+
+fn default_progress_save_delay_ms() -> u64 {
+    DEFAULT_PROGRESS_SAVE_DELAY_MS
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
@@ -98,6 +254,11 @@ impl Default for Settings {
             aspect_ratio: "auto".to_string(),
             custom_aspect_width: 3,
             custom_aspect_height: 4,
+            page_cache_size_mb: DEFAULT_PAGE_CACHE_MB,
+            auto_crop: false,
+            spread_split: SpreadSplit::Off,
+            progress_save_delay_ms: DEFAULT_PROGRESS_SAVE_DELAY_MS,
+            language: None,
         }
     }
 }
@@ -111,8 +272,10 @@ pub struct OpenedDirectory {
 }
 
 /// 应用数据
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppData {
+    /// 数据格式版本，读取时按迁移链升级到 DATA_VERSION
+    pub version: u32,
     pub settings: Settings,
     pub progress: HashMap<String, ReadingProgress>,
     pub bookmarks: Vec<Bookmark>,
@@ -120,18 +283,114 @@ pub struct AppData {
     pub opened_directories: Vec<OpenedDirectory>,
 }
 
+impl Default for AppData {
+    fn default() -> Self {
+        Self {
+            version: DATA_VERSION,
+            settings: Settings::default(),
+            progress: HashMap::new(),
+            bookmarks: Vec::new(),
+            opened_directories: Vec::new(),
+        }
+    }
+}
+
+/// 版本 1 → 2：补齐旧版本依赖 serde 默认值省略的字段
+///
+/// 默认值按版本 2 的格式写死，不引用 Settings::default()，以后修改默认值不会改变这一步迁移的结果；
+/// language 缺省即为跟随系统，交给 serde 处理
+fn migrate_v1_to_v2(data: &mut Map<String, Value>) {
+    let defaults = json!({
+        "theme": "system",
+        "zoomMode": "fit-width",
+        "customZoom": 100.0,
+        "preloadCount": 3,
+        "readerMode": "embedded",
+        "aspectRatio": "auto",
+        "customAspectWidth": 3,
+        "customAspectHeight": 4,
+        "pageCacheSizeMb": 256,
+        "autoCrop": false,
+        "spreadSplit": "off",
+    });
+    let settings = data
+        .entry("settings")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let (Some(settings), Value::Object(defaults)) = (settings.as_object_mut(), defaults) {
+        for (key, value) in defaults {
+            settings.entry(key).or_insert(value);
+        }
+    }
+
+    if let Some(progress) = data.get_mut("progress").and_then(Value::as_object_mut) {
+        for entry in progress.values_mut().filter_map(Value::as_object_mut) {
+            for key in ["zoomMode", "customZoom", "spreadMode"] {
+                entry.entry(key).or_insert(Value::Null);
+            }
+        }
+    }
+
+    data.entry("progress").or_insert_with(|| Value::Object(Map::new()));
+    data.entry("bookmarks").or_insert_with(|| Value::Array(Vec::new()));
+    data.entry("openedDirectories").or_insert_with(|| Value::Array(Vec::new()));
+}
+
+/// 读取数据文件，必要时按迁移链升级
+///
+/// 迁移前先把原文件备份为 `comic_data.v{版本}.bak.json`，
+/// 文件版本高于 DATA_VERSION 时直接报错，避免按默认值读取后覆盖掉新版本写入的数据
+fn read_data_file(file_path: &Path) -> Result<AppData, ComicError> {
+    let content = fs::read_to_string(file_path).map_err(|e| ComicError::io(file_path, e))?;
+    let mut value: Value = serde_json::from_str(&content)?;
+
+    let Some(data) = value.as_object_mut() else {
+        return Ok(serde_json::from_value(value)?);
+    };
+
+    let version = data
+        .get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v.max(1) as u32)
+        .unwrap_or(1);
+
+    if version > DATA_VERSION {
+        return Err(ComicError::DataVersionTooNew {
+            path: file_path.to_string_lossy().into_owned(),
+            version,
+            supported: DATA_VERSION,
+        });
+    }
+
+    if version < DATA_VERSION {
+        let backup_path = file_path.with_extension(format!("v{}.bak.json", version));
+        fs::copy(file_path, &backup_path).map_err(|e| ComicError::io(&backup_path, e))?;
+
+        for migrate in &MIGRATIONS[(version - 1) as usize..] {
+            migrate(data);
+        }
+        data.insert("version".to_string(), Value::from(DATA_VERSION));
+
+        eprintln!(
+            "{}",
+            tr(Msg::WarnDataMigrated, &[&version, &DATA_VERSION, &backup_path.display()])
+        );
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
 /// 获取数据目录（程序同目录）
-fn get_data_dir(app: &AppHandle) -> PathBuf {
+pub fn get_data_dir(app: &AppHandle) -> PathBuf {
     app.path()
         .resource_dir()
-        .expect("无法获取程序目录")
+        .unwrap_or_else(|e| panic!("{}", tr(Msg::NoResourceDir, &[&e])))
 }
 
 /// 旧版本数据目录（app_data_dir）
 fn get_legacy_data_dir(app: &AppHandle) -> PathBuf {
     app.path()
         .app_data_dir()
-        .expect("无法获取旧版应用数据目录")
+        .unwrap_or_else(|e| panic!("{}", tr(Msg::NoAppDataDir, &[&e])))
 }
 
 /// 获取数据文件路径
@@ -145,127 +404,359 @@ fn get_legacy_data_file_path(app: &AppHandle) -> PathBuf {
 }
 
 /// 确保数据目录存在
-fn ensure_data_dir(app: &AppHandle) -> Result<(), String> {
+pub fn ensure_data_dir(app: &AppHandle) -> Result<(), ComicError> {
     let dir = get_data_dir(app);
     if !dir.exists() {
-        fs::create_dir_all(&dir).map_err(|e| format!("无法创建数据目录: {}", e))?;
+        fs::create_dir_all(&dir).map_err(|e| ComicError::io(&dir, e))?;
+    }
+    Ok(())
+}
+
+/// 打开数据库并初始化表结构；新建的数据库会先导入 comic_data.json
+fn open_database(app: &AppHandle) -> Result<Connection, ComicError> {
+    ensure_data_dir(app)?;
+    let db_path = get_data_dir(app).join(STORAGE_DB_FILE);
+    let mut conn = Connection::open(&db_path)?;
+
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         CREATE TABLE IF NOT EXISTS settings (
+             key   TEXT PRIMARY KEY,
+             value TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS progress (
+             comic_path       TEXT PRIMARY KEY,
+             last_image_index INTEGER NOT NULL,
+             scroll_position  REAL NOT NULL,
+             last_read_time   INTEGER NOT NULL,
+             zoom_mode        TEXT,
+             custom_zoom      REAL,
+             spread_mode      TEXT
+         );
+         CREATE TABLE IF NOT EXISTS bookmarks (
+             id          TEXT PRIMARY KEY,
+             comic_path  TEXT NOT NULL,
+             comic_name  TEXT NOT NULL,
+             image_index INTEGER NOT NULL,
+             created_at  INTEGER NOT NULL,
+             note        TEXT
+         );
+         CREATE INDEX IF NOT EXISTS bookmarks_comic_path ON bookmarks (comic_path);
+         CREATE TABLE IF NOT EXISTS opened_directories (
+             path      TEXT PRIMARY KEY,
+             opened_at INTEGER NOT NULL
+         );",
+    )?;
+
+    let schema_version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if schema_version == 0 {
+        import_json(app, &mut conn)?;
+    }
+
+    Ok(conn)
+}
+
+/// 一次性导入 comic_data.json（新目录优先，其次旧版目录），导入后把文件改名保留
+///
+/// 导入与标记 user_version 在同一事务中完成，失败时下次打开会重新导入
+fn import_json(app: &AppHandle, conn: &mut Connection) -> Result<(), ComicError> {
+    let file_path = [get_data_file_path(app), get_legacy_data_file_path(app)]
+        .into_iter()
+        .find(|path| path.exists());
+
+    let tx = conn.transaction()?;
+    if let Some(file_path) = &file_path {
+        let data = read_data_file(file_path)?;
+        write_settings(&tx, &data.settings)?;
+        for progress in data.progress.values() {
+            write_progress(&tx, progress)?;
+        }
+        for bookmark in &data.bookmarks {
+            write_bookmark(&tx, bookmark)?;
+        }
+        for directory in &data.opened_directories {
+            write_opened_directory(&tx, directory)?;
+        }
+    }
+    tx.pragma_update(None, "user_version", STORAGE_SCHEMA_VERSION)?;
+    tx.commit()?;
+
+    if let Some(file_path) = file_path {
+        let imported_path = file_path.with_extension("imported.json");
+        if let Err(e) = fs::rename(&file_path, &imported_path) {
+            eprintln!("{}", tr(Msg::WarnRenameImportedData, &[&file_path.display(), &e]));
+        }
+    }
+    Ok(())
+}
+
+/// 获取数据库连接（首次使用时打开）
+fn with_storage<T>(
+    app: &AppHandle,
+    cache: &AppDataCache,
+    f: impl FnOnce(&mut Connection) -> Result<T, ComicError>,
+) -> Result<T, ComicError> {
+    let mut guard = cache.conn.lock()?;
+    if guard.is_none() {
+        *guard = Some(open_database(app)?);
+    }
+    f(guard.as_mut().unwrap())
+}
+
+/// 更新内存缓存；尚未加载时跳过，下次加载会直接读取数据库
+fn update_cache(cache: &AppDataCache, f: impl FnOnce(&mut AppData)) -> Result<(), ComicError> {
+    if let Some(data) = cache.data.lock()?.as_mut() {
+        f(data);
+    }
+    Ok(())
+}
+
+/// 设置按字段逐项保存，值为 JSON；读取时缺少的字段使用默认值
+fn write_settings(conn: &Connection, settings: &Settings) -> Result<(), ComicError> {
+    let Value::Object(fields) = serde_json::to_value(settings)? else {
+        return Ok(());
+    };
+    let mut stmt = conn.prepare_cached("INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)")?;
+    for (key, value) in fields {
+        stmt.execute(params![key, value.to_string()])?;
     }
     Ok(())
 }
 
+fn read_settings(conn: &Connection) -> Result<Settings, ComicError> {
+    let Value::Object(mut fields) = serde_json::to_value(Settings::default())? else {
+        return Ok(Settings::default());
+    };
+    let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (key, value) = row?;
+        fields.insert(key, serde_json::from_str(&value)?);
+    }
+    Ok(serde_json::from_value(Value::Object(fields))?)
+}
+
+fn write_progress(conn: &Connection, progress: &ReadingProgress) -> Result<(), ComicError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO progress
+             (comic_path, last_image_index, scroll_position, last_read_time, zoom_mode, custom_zoom, spread_mode)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            progress.comic_path,
+            progress.last_image_index,
+            progress.scroll_position,
+            progress.last_read_time,
+            progress.zoom_mode,
+            progress.custom_zoom,
+            progress.spread_mode,
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_progress(conn: &Connection) -> Result<HashMap<String, ReadingProgress>, ComicError> {
+    let mut stmt = conn.prepare(
+        "SELECT comic_path, last_image_index, scroll_position, last_read_time, zoom_mode, custom_zoom, spread_mode
+         FROM progress",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ReadingProgress {
+            comic_path: row.get(0)?,
+            last_image_index: row.get(1)?,
+            scroll_position: row.get(2)?,
+            last_read_time: row.get(3)?,
+            zoom_mode: row.get(4)?,
+            custom_zoom: row.get(5)?,
+            spread_mode: row.get(6)?,
+        })
+    })?;
+
+    let mut progress = HashMap::new();
+    for row in rows {
+        let entry = row?;
+        progress.insert(entry.comic_path.clone(), entry);
+    }
+    Ok(progress)
+}
+
+fn write_bookmark(conn: &Connection, bookmark: &Bookmark) -> Result<(), ComicError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO bookmarks (id, comic_path, comic_name, image_index, created_at, note)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            bookmark.id,
+            bookmark.comic_path,
+            bookmark.comic_name,
+            bookmark.image_index,
+            bookmark.created_at,
+            bookmark.note,
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_bookmarks(conn: &Connection) -> Result<Vec<Bookmark>, ComicError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, comic_path, comic_name, image_index, created_at, note FROM bookmarks ORDER BY rowid",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Bookmark {
+            id: row.get(0)?,
+            comic_path: row.get(1)?,
+            comic_name: row.get(2)?,
+            image_index: row.get(3)?,
+            created_at: row.get(4)?,
+            note: row.get(5)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn write_opened_directory(conn: &Connection, directory: &OpenedDirectory) -> Result<(), ComicError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO opened_directories (path, opened_at) VALUES (?1, ?2)",
+        params![directory.path, directory.opened_at],
+    )?;
+    Ok(())
+}
+
+fn read_opened_directories(conn: &Connection) -> Result<Vec<OpenedDirectory>, ComicError> {
+    let mut stmt = conn.prepare("SELECT path, opened_at FROM opened_directories ORDER BY opened_at DESC")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(OpenedDirectory {
+            path: row.get(0)?,
+            opened_at: row.get(1)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
 /// 加载应用数据（优先从缓存读取）
-pub fn load_app_data(app: &AppHandle, cache: &AppDataCache) -> Result<AppData, String> {
-    let guard = cache.0.lock().map_err(|e| format!("锁获取失败: {}", e))?;
+pub fn load_app_data(app: &AppHandle, cache: &AppDataCache) -> Result<AppData, ComicError> {
+    let mut guard = cache.data.lock()?;
 
     if let Some(data) = guard.as_ref() {
         return Ok(data.clone());
     }
 
-    // 释放锁，避免在文件操作时持有锁
-    drop(guard);
-
-    let file_path = get_data_file_path(app);
-    let legacy_file_path = get_legacy_data_file_path(app);
-
-    let data = if file_path.exists() {
-        // 新目录存在，直接读取
-        let content = fs::read_to_string(&file_path)
-            .map_err(|e| format!("无法读取数据文件: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("无法解析数据: {}", e))?
-    } else if legacy_file_path.exists() {
-        // 旧目录存在，迁移数据
-        let content = fs::read_to_string(&legacy_file_path)
-            .map_err(|e| format!("无法读取旧版数据文件: {}", e))?;
-        let data: AppData = serde_json::from_str(&content).map_err(|e| format!("无法解析旧版数据: {}", e))?;
-        
-        // 保存到新目录（此时锁已释放，不会死锁）
-        save_app_data(app, cache, &data)?;
-        
-        // 删除旧数据文件
-        if let Err(e) = fs::remove_file(&legacy_file_path) {
-            eprintln!("警告：无法删除旧数据文件: {}", e);
-        }
-        
-        data
-    } else {
-        // 都不存在，返回默认值
-        AppData::default()
-    };
+    let mut data = with_storage(app, cache, |conn| {
+        Ok(AppData {
+            version: DATA_VERSION,
+            settings: read_settings(conn)?,
+            progress: read_progress(conn)?,
+            bookmarks: read_bookmarks(conn)?,
+            opened_directories: read_opened_directories(conn)?,
+        })
+    })?;
+
+    // 尚未写入数据库的进度以队列中的为准
+    for progress in cache.progress_queue.pending()? {
+        data.progress.insert(progress.comic_path.clone(), progress);
+    }
 
-    // 重新获取锁并更新缓存
-    let mut guard = cache.0.lock().map_err(|e| format!("锁获取失败: {}", e))?;
     *guard = Some(data.clone());
     Ok(data)
 }
 
-/// 原子写入：写临时文件 + rename，防止崩溃时数据损坏
-fn atomic_write(file_path: &PathBuf, content: &str) -> Result<(), String> {
-    let tmp_path = file_path.with_extension("json.tmp");
-    fs::write(&tmp_path, content).map_err(|e| format!("无法写入临时文件: {}", e))?;
-    fs::rename(&tmp_path, file_path).map_err(|e| format!("无法重命名临时文件: {}", e))?;
-    Ok(())
+/// 设置阅读进度的合并写入窗口（毫秒），0 为每次立即写入
+pub fn set_progress_save_delay(cache: &AppDataCache, delay_ms: u64) {
+    cache.progress_delay_ms.store(delay_ms, Ordering::Relaxed);
 }
 
-/// 保存应用数据（更新缓存 + 原子写盘）
-pub fn save_app_data(app: &AppHandle, cache: &AppDataCache, data: &AppData) -> Result<(), String> {
-    ensure_data_dir(app)?;
+/// 保存阅读进度：立即更新缓存，数据库写入交给后台线程在写入窗口结束后合并进行
+///
+/// 写入窗口为 0 时同样经过队列并立即写入，避免队列中较旧的进度随后覆盖本次写入
+pub fn save_progress(app: &AppHandle, cache: &AppDataCache, progress: ReadingProgress) -> Result<(), ComicError> {
+    cache.progress_queue.push(progress.clone())?;
+    if cache.progress_delay_ms.load(Ordering::Relaxed) == 0 {
+        flush_progress(app, cache)?;
+    }
 
-    // 更新缓存
-    let mut guard = cache.0.lock().map_err(|e| format!("锁获取失败: {}", e))?;
-    *guard = Some(data.clone());
-    drop(guard);
+    update_cache(cache, |data| {
+        data.progress.insert(progress.comic_path.clone(), progress);
+    })
+}
 
-    // 原子写盘
-    let file_path = get_data_file_path(app);
-    let content = serde_json::to_string_pretty(data)
-        .map_err(|e| format!("无法序列化数据: {}", e))?;
-    atomic_write(&file_path, &content)
+/// 把待写入的阅读进度在一个事务中写入数据库；后台线程正在写入时先等待其完成
+pub fn flush_progress(app: &AppHandle, cache: &AppDataCache) -> Result<(), ComicError> {
+    cache.progress_queue.flush(|batch| {
+        with_storage(app, cache, |conn| {
+            let tx = conn.transaction()?;
+            for progress in batch.values() {
+                write_progress(&tx, progress)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    })
 }
 
-/// 保存阅读进度
-pub fn save_progress(app: &AppHandle, cache: &AppDataCache, progress: ReadingProgress) -> Result<(), String> {
-    let mut data = load_app_data(app, cache)?;
-    data.progress.insert(progress.comic_path.clone(), progress);
-    save_app_data(app, cache, &data)
+/// 退出前调用：停止后台写入线程，等待正在进行的写入完成，再写入队列中剩余的进度
+pub fn shutdown_progress_writer(app: &AppHandle, cache: &AppDataCache) -> Result<(), ComicError> {
+    cache.progress_queue.stop()?;
+    flush_progress(app, cache)
+}
+
+/// 启动阅读进度的后台写入线程：收到进度后等待一个写入窗口，把窗口内同一漫画的多次更新合并为一次写入
+pub fn start_progress_writer(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || {
+        let cache = app.state::<AppDataCache>();
+        loop {
+            let delay = Duration::from_millis(cache.progress_delay_ms.load(Ordering::Relaxed));
+            if !cache.progress_queue.wait_for_batch(delay) {
+                return;
+            }
+            if let Err(e) = flush_progress(&app, &cache) {
+                eprintln!("{}", tr(Msg::Warning, &[&e]));
+            }
+        }
+    });
 }
 
 /// 获取阅读进度
-pub fn get_progress(app: &AppHandle, cache: &AppDataCache, comic_path: &str) -> Result<Option<ReadingProgress>, String> {
+pub fn get_progress(app: &AppHandle, cache: &AppDataCache, comic_path: &str) -> Result<Option<ReadingProgress>, ComicError> {
     let data = load_app_data(app, cache)?;
     Ok(data.progress.get(comic_path).cloned())
 }
 
-/// 添加书签
-pub fn add_bookmark(app: &AppHandle, cache: &AppDataCache, bookmark: Bookmark) -> Result<(), String> {
-    let mut data = load_app_data(app, cache)?;
-
-    let exists = data.bookmarks.iter().any(|b| {
-        b.comic_path == bookmark.comic_path && b.image_index == bookmark.image_index
-    });
+/// 添加书签（同一页已有书签时忽略）
+pub fn add_bookmark(app: &AppHandle, cache: &AppDataCache, bookmark: Bookmark) -> Result<(), ComicError> {
+    let added = with_storage(app, cache, |conn| {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM bookmarks WHERE comic_path = ?1 AND image_index = ?2)",
+            params![bookmark.comic_path, bookmark.image_index],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            write_bookmark(conn, &bookmark)?;
+        }
+        Ok(!exists)
+    })?;
 
-    if !exists {
-        data.bookmarks.push(bookmark);
-        save_app_data(app, cache, &data)?;
+    if added {
+        update_cache(cache, |data| data.bookmarks.push(bookmark))?;
     }
-
     Ok(())
 }
 
 /// 删除书签
-pub fn remove_bookmark(app: &AppHandle, cache: &AppDataCache, bookmark_id: &str) -> Result<(), String> {
-    let mut data = load_app_data(app, cache)?;
-    data.bookmarks.retain(|b| b.id != bookmark_id);
-    save_app_data(app, cache, &data)
+pub fn remove_bookmark(app: &AppHandle, cache: &AppDataCache, bookmark_id: &str) -> Result<(), ComicError> {
+    with_storage(app, cache, |conn| {
+        conn.execute("DELETE FROM bookmarks WHERE id = ?1", params![bookmark_id])?;
+        Ok(())
+    })?;
+    update_cache(cache, |data| data.bookmarks.retain(|b| b.id != bookmark_id))
 }
 
 /// 获取所有书签
-pub fn get_bookmarks(app: &AppHandle, cache: &AppDataCache) -> Result<Vec<Bookmark>, String> {
+pub fn get_bookmarks(app: &AppHandle, cache: &AppDataCache) -> Result<Vec<Bookmark>, ComicError> {
     let data = load_app_data(app, cache)?;
     Ok(data.bookmarks)
 }
 
 /// 获取漫画的书签
-pub fn get_comic_bookmarks(app: &AppHandle, cache: &AppDataCache, comic_path: &str) -> Result<Vec<Bookmark>, String> {
+pub fn get_comic_bookmarks(app: &AppHandle, cache: &AppDataCache, comic_path: &str) -> Result<Vec<Bookmark>, ComicError> {
     let data = load_app_data(app, cache)?;
     Ok(data
         .bookmarks
@@ -275,51 +766,287 @@ pub fn get_comic_bookmarks(app: &AppHandle, cache: &AppDataCache, comic_path: &s
 }
 
 /// 保存设置
-pub fn save_settings(app: &AppHandle, cache: &AppDataCache, settings: Settings) -> Result<(), String> {
-    let mut data = load_app_data(app, cache)?;
-    data.settings = settings;
-    save_app_data(app, cache, &data)
+pub fn save_settings(app: &AppHandle, cache: &AppDataCache, settings: Settings) -> Result<(), ComicError> {
+    with_storage(app, cache, |conn| {
+        let tx = conn.transaction()?;
+        write_settings(&tx, &settings)?;
+        tx.commit()?;
+        Ok(())
+    })?;
+    update_cache(cache, |data| data.settings = settings)
 }
 
 /// 获取设置
-pub fn get_settings(app: &AppHandle, cache: &AppDataCache) -> Result<Settings, String> {
+pub fn get_settings(app: &AppHandle, cache: &AppDataCache) -> Result<Settings, ComicError> {
     let data = load_app_data(app, cache)?;
     Ok(data.settings)
 }
 
-/// 保存打开的目录
-pub fn save_opened_directory(app: &AppHandle, cache: &AppDataCache, path: &str) -> Result<(), String> {
-    let mut data = load_app_data(app, cache)?;
-    
-    // 如果路径已存在，先移除
-    data.opened_directories.retain(|d| d.path != path);
-    
-    // 添加到列表开头
-    data.opened_directories.insert(0, OpenedDirectory {
+/// 保存打开的目录（移到最前，最多保留 MAX_OPENED_DIRECTORIES 个）
+pub fn save_opened_directory(app: &AppHandle, cache: &AppDataCache, path: &str) -> Result<(), ComicError> {
+    let directory = OpenedDirectory {
         path: path.to_string(),
         opened_at: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_millis() as u64,
-    });
-    
-    // 限制最多保存 10 个目录
-    if data.opened_directories.len() > 10 {
-        data.opened_directories.truncate(10);
-    }
-    
-    save_app_data(app, cache, &data)
+    };
+
+    with_storage(app, cache, |conn| {
+        let tx = conn.transaction()?;
+        write_opened_directory(&tx, &directory)?;
+        tx.execute(
+            "DELETE FROM opened_directories WHERE path NOT IN
+                 (SELECT path FROM opened_directories ORDER BY opened_at DESC LIMIT ?1)",
+            params![MAX_OPENED_DIRECTORIES],
+        )?;
+        tx.commit()?;
+        Ok(())
+    })?;
+
+    update_cache(cache, |data| {
+        data.opened_directories.retain(|d| d.path != path);
+        data.opened_directories.insert(0, directory);
+        data.opened_directories.truncate(MAX_OPENED_DIRECTORIES);
+    })
 }
 
 /// 移除打开的目录
-pub fn remove_opened_directory(app: &AppHandle, cache: &AppDataCache, path: &str) -> Result<(), String> {
-    let mut data = load_app_data(app, cache)?;
-    data.opened_directories.retain(|d| d.path != path);
-    save_app_data(app, cache, &data)
+pub fn remove_opened_directory(app: &AppHandle, cache: &AppDataCache, path: &str) -> Result<(), ComicError> {
+    with_storage(app, cache, |conn| {
+        conn.execute("DELETE FROM opened_directories WHERE path = ?1", params![path])?;
+        Ok(())
+    })?;
+    update_cache(cache, |data| data.opened_directories.retain(|d| d.path != path))
 }
 
 /// 获取所有打开的目录
-pub fn get_opened_directories(app: &AppHandle, cache: &AppDataCache) -> Result<Vec<OpenedDirectory>, String> {
+pub fn get_opened_directories(app: &AppHandle, cache: &AppDataCache) -> Result<Vec<OpenedDirectory>, ComicError> {
     let data = load_app_data(app, cache)?;
     Ok(data.opened_directories)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDir;
+    use std::sync::mpsc;
+
+    /// 在临时目录中写入数据文件夹具，返回目录（离开作用域时删除）和数据文件路径
+    fn data_fixture(name: &str, content: &str) -> (TestDir, PathBuf) {
+        let dir = TestDir::new(&format!("storage-{}", name));
+        let file_path = dir.write("comic_data.json", content);
+        (dir, file_path)
+    }
+
+    fn progress(path: &str, index: usize) -> ReadingProgress {
+        ReadingProgress {
+            comic_path: path.to_string(),
+            last_image_index: index,
+            scroll_position: 0.0,
+            last_read_time: 0,
+            zoom_mode: None,
+            custom_zoom: None,
+            spread_mode: None,
+        }
+    }
+
+    #[test]
+    fn progress_queue_coalesces_updates() {
+        let queue = ProgressQueue::default();
+        queue.push(progress("a", 1)).unwrap();
+        queue.push(progress("a", 2)).unwrap();
+        queue.push(progress("b", 7)).unwrap();
+
+        let mut written = Vec::new();
+        queue
+            .flush(|batch| {
+                written.extend(batch.values().map(|p| (p.comic_path.clone(), p.last_image_index)));
+                Ok(())
+            })
+            .unwrap();
+        written.sort();
+
+        assert_eq!(written, vec![("a".to_string(), 2), ("b".to_string(), 7)]);
+        assert!(queue.pending().unwrap().is_empty());
+    }
+
+    #[test]
+    fn progress_queue_failed_flush_keeps_newer_entries() {
+        let queue = ProgressQueue::default();
+        queue.push(progress("a", 1)).unwrap();
+
+        let result = queue.flush(|_| {
+            // 写入期间又收到一条更新的进度
+            queue.push(progress("a", 5)).unwrap();
+            Err(ComicError::LockPoisoned)
+        });
+        assert!(result.is_err());
+
+        let pending = queue.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].last_image_index, 5);
+    }
+
+    #[test]
+    fn progress_queue_pending_includes_in_flight_batch() {
+        let queue = ProgressQueue::default();
+        queue.push(progress("a", 1)).unwrap();
+        queue.push(progress("b", 2)).unwrap();
+
+        queue
+            .flush(|_| {
+                // 写入期间读取仍能看到这批进度，且新的更新优先
+                queue.push(progress("a", 3)).unwrap();
+                let mut pending: Vec<_> = queue
+                    .pending()
+                    .unwrap()
+                    .into_iter()
+                    .map(|p| (p.comic_path, p.last_image_index))
+                    .collect();
+                pending.sort();
+                assert_eq!(pending, vec![("a".to_string(), 3), ("b".to_string(), 2)]);
+                Ok(())
+            })
+            .unwrap();
+
+        let pending = queue.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].last_image_index, 3);
+    }
+
+    #[test]
+    fn progress_queue_final_flush_waits_for_in_flight_batch() {
+        let queue = Arc::new(ProgressQueue::default());
+        let order = Arc::new(Mutex::new(Vec::new()));
+        queue.push(progress("a", 1)).unwrap();
+
+        // 后台写入取走队列后卡在写入中
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let background = {
+            let queue = queue.clone();
+            let order = order.clone();
+            thread::spawn(move || {
+                queue
+                    .flush(|batch| {
+                        started_tx.send(()).unwrap();
+                        release_rx.recv().unwrap();
+                        order.lock().unwrap().push(("background", batch.len()));
+                        Ok(())
+                    })
+                    .unwrap();
+            })
+        };
+        started_rx.recv().unwrap();
+
+        // 此时退出：队列为空，但最后一次写入必须等后台写入完成
+        queue.push(progress("b", 2)).unwrap();
+        queue.stop().unwrap();
+        let final_flush = {
+            let queue = queue.clone();
+            let order = order.clone();
+            thread::spawn(move || {
+                queue
+                    .flush(|batch| {
+                        order.lock().unwrap().push(("final", batch.len()));
+                        Ok(())
+                    })
+                    .unwrap();
+            })
+        };
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(order.lock().unwrap().is_empty());
+        release_tx.send(()).unwrap();
+        background.join().unwrap();
+        final_flush.join().unwrap();
+
+        assert_eq!(*order.lock().unwrap(), vec![("background", 1), ("final", 1)]);
+    }
+
+    #[test]
+    fn progress_queue_stop_wakes_writer() {
+        let queue = Arc::new(ProgressQueue::default());
+        let writer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.wait_for_batch(Duration::from_secs(60)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        queue.stop().unwrap();
+        assert!(!writer.join().unwrap());
+    }
+
+    #[test]
+    fn progress_queue_stop_cuts_write_window_short() {
+        let queue = Arc::new(ProgressQueue::default());
+        queue.push(progress("a", 1)).unwrap();
+        let writer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.wait_for_batch(Duration::from_secs(60)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        queue.stop().unwrap();
+        assert!(!writer.join().unwrap());
+        assert_eq!(queue.pending().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn read_data_file_migrates_v1_and_keeps_a_backup() {
+        let v1 = r#"{
+            "settings": { "theme": "dark", "zoomMode": "custom", "customZoom": 150.0, "preloadCount": 5 },
+            "progress": {
+                "/comics/a.cbz": { "comicPath": "/comics/a.cbz", "lastImageIndex": 12, "scrollPosition": 0.5, "lastReadTime": 1 }
+            }
+        }"#;
+        let (_dir, file_path) = data_fixture("v1", v1);
+
+        let data = read_data_file(&file_path).unwrap();
+        assert_eq!(data.version, DATA_VERSION);
+        assert_eq!(data.settings.theme, "dark");
+        assert_eq!(data.settings.custom_zoom, 150.0);
+        assert_eq!(data.settings.reader_mode, "embedded");
+        assert_eq!(data.settings.custom_aspect_width, 3);
+        assert_eq!(data.settings.page_cache_size_mb, 256);
+        assert_eq!(data.settings.spread_split, SpreadSplit::Off);
+        assert_eq!(data.progress["/comics/a.cbz"].last_image_index, 12);
+        assert_eq!(data.progress["/comics/a.cbz"].zoom_mode, None);
+        assert!(data.bookmarks.is_empty());
+        assert!(data.opened_directories.is_empty());
+
+        let backup = file_path.with_extension("v1.bak.json");
+        assert_eq!(fs::read_to_string(backup).unwrap(), v1);
+    }
+
+    #[test]
+    fn read_data_file_reads_current_version_without_backup() {
+        let current = format!(
+            r#"{{ "version": {}, "settings": {{ "theme": "light", "zoomMode": "fit-height", "customZoom": 80.0, "preloadCount": 2 }}, "progress": {{}}, "bookmarks": [] }}"#,
+            DATA_VERSION
+        );
+        let (_dir, file_path) = data_fixture("current", &current);
+
+        let data = read_data_file(&file_path).unwrap();
+        assert_eq!(data.version, DATA_VERSION);
+        assert_eq!(data.settings.zoom_mode, "fit-height");
+        let files = fs::read_dir(file_path.parent().unwrap()).unwrap().count();
+        assert_eq!(files, 1);
+    }
+
+    #[test]
+    fn read_data_file_rejects_newer_versions() {
+        let newer = format!(r#"{{ "version": {}, "settings": {{}} }}"#, DATA_VERSION + 1);
+        let (_dir, file_path) = data_fixture("newer", &newer);
+
+        let err = read_data_file(&file_path).unwrap_err();
+        assert_eq!(
+            err,
+            ComicError::DataVersionTooNew {
+                path: file_path.to_string_lossy().into_owned(),
+                version: DATA_VERSION + 1,
+                supported: DATA_VERSION,
+            }
+        );
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), newer);
+    }
+}
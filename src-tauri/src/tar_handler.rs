@@ -0,0 +1,141 @@
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tar::Archive;
+use xz2::read::XzDecoder;
+
+use crate::comic_source::{build_page_list, page_positions, ComicSource, PageInfo};
+use crate::error::ComicError;
+use crate::file_system::ArchiveKind;
+use crate::image_handler::get_image_dimensions_from_reader;
+
+/// gzip 文件头魔数
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// xz 文件头魔数
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// TAR 漫画来源：tar 只能顺序读取，无法保留可随机访问的句柄，
+/// 因此只缓存图片列表，每次读取时重新遍历
+pub struct TarSource {
+    path: String,
+    pages: Vec<PageInfo>,
+}
+
+/// 打开 TAR 文件，根据文件头自动识别 gzip/xz 压缩
+fn open_tar(tar_path: &str) -> Result<Archive<Box<dyn Read>>, ComicError> {
+    let mut file = File::open(tar_path).map_err(|e| ComicError::io(tar_path, e))?;
+
+    let mut magic = [0u8; 6];
+    let read = file.read(&mut magic).map_err(|e| ComicError::io(tar_path, e))?;
+    file.seek(SeekFrom::Start(0)).map_err(|e| ComicError::io(tar_path, e))?;
+
+    let reader = BufReader::new(file);
+    let reader: Box<dyn Read> = if magic[..read].starts_with(GZIP_MAGIC) {
+        Box::new(MultiGzDecoder::new(reader))
+    } else if magic[..read].starts_with(XZ_MAGIC) {
+        Box::new(XzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    };
+
+    Ok(Archive::new(reader))
+}
+
+impl TarSource {
+    /// 遍历 TAR 条目，读取图片列表
+    pub fn open(tar_path: &str) -> Result<Self, ComicError> {
+        if !Path::new(tar_path).exists() {
+            return Err(ComicError::not_found(tar_path));
+        }
+
+        let mut archive = open_tar(tar_path)?;
+
+        let mut entries = Vec::new();
+
+        for entry in archive.entries().map_err(|e| ComicError::corrupt(tar_path, e))? {
+            let entry = entry.map_err(|e| ComicError::corrupt(tar_path, e))?;
+
+            // 只处理普通文件
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let file_path = entry
+                .path()
+                .map_err(|e| ComicError::corrupt(tar_path, e))?
+                .to_string_lossy()
+                .to_string();
+            entries.push((file_path, entry.size()));
+        }
+
+        Ok(Self {
+            path: tar_path.to_string(),
+            pages: build_page_list(entries),
+        })
+    }
+}
+
+impl ComicSource for TarSource {
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        Some(ArchiveKind::Tar)
+    }
+
+    fn pages(&self) -> &[PageInfo] {
+        &self.pages
+    }
+
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError> {
+        let mut archive = open_tar(&self.path)?;
+
+        for entry in archive.entries().map_err(|e| ComicError::corrupt(&self.path, e))? {
+            let mut entry = entry.map_err(|e| ComicError::corrupt(&self.path, e))?;
+
+            let matches = entry
+                .path()
+                .map(|p| p.to_string_lossy() == page.path)
+                .unwrap_or(false);
+
+            if matches {
+                let mut buffer = Vec::with_capacity(entry.size() as usize);
+                entry
+                    .read_to_end(&mut buffer)
+                    .map_err(|e| ComicError::corrupt(&self.path, e))?;
+                return Ok(buffer);
+            }
+        }
+
+        Err(ComicError::entry_not_found(&self.path, &page.path))
+    }
+
+    fn all_page_dimensions(&mut self) -> Vec<Option<(u32, u32)>> {
+        let mut dimensions = vec![None; self.pages.len()];
+        let positions = page_positions(&self.pages);
+
+        let Ok(mut archive) = open_tar(&self.path) else {
+            return dimensions;
+        };
+        let Ok(entries) = archive.entries() else {
+            return dimensions;
+        };
+
+        // 单次遍历，每个图片只读取文件头
+        for mut entry in entries.flatten() {
+            let position = entry
+                .path()
+                .ok()
+                .and_then(|p| positions.get(p.to_string_lossy().as_ref()).copied());
+
+            if let Some(position) = position {
+                dimensions[position] = get_image_dimensions_from_reader(&mut entry).ok();
+            }
+        }
+
+        dimensions
+    }
+}
@@ -0,0 +1,265 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::comic_source::{get_cover_page_index, read_page, render_page, SourceCache};
+use crate::error::ComicError;
+use crate::i18n::{tr, Msg};
+use crate::file_system::{comic_stamp, ArchiveKind};
+use crate::image_handler::make_thumbnail;
+
+/// 缩略图缓存目录（位于应用缓存目录下）
+const THUMBNAIL_DIR: &str = "thumbnails";
+
+/// 缩略图缓存的磁盘空间上限
+const MAX_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// 超出上限时清理到上限的 90%，避免每次写入都触发清理
+const EVICT_TARGET_BYTES: u64 = MAX_CACHE_BYTES / 10 * 9;
+
+/// 缩略图的 JPEG 质量
+const THUMBNAIL_QUALITY: u8 = 85;
+
+/// 缩略图边长上限
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+/// 缩略图缓存：首次使用时扫描缓存目录建立索引
+pub struct ThumbnailCache(pub Mutex<Option<ThumbnailIndex>>);
+
+impl Default for ThumbnailCache {
+    fn default() -> Self {
+        Self(Mutex::new(None))
+    }
+}
+
+/// 缓存索引：文件名 -> (大小, 最近使用时间)
+pub struct ThumbnailIndex {
+    dir: PathBuf,
+    entries: HashMap<String, (u64, SystemTime)>,
+    total_bytes: u64,
+}
+
+impl ThumbnailIndex {
+    /// 扫描缓存目录，以文件修改时间作为最近使用时间
+    fn load(dir: PathBuf) -> Result<Self, ComicError> {
+        fs::create_dir_all(&dir).map_err(|e| ComicError::io(&dir, e))?;
+
+        let mut entries = HashMap::new();
+        let mut total_bytes = 0;
+
+        if let Ok(dir_entries) = fs::read_dir(&dir) {
+            for entry in dir_entries.flatten() {
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                if !metadata.is_file() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                // 清理上次异常退出残留的临时文件
+                if name.ends_with(".tmp") {
+                    let _ = fs::remove_file(entry.path());
+                    continue;
+                }
+                let last_used = metadata.modified().unwrap_or(UNIX_EPOCH);
+                total_bytes += metadata.len();
+                entries.insert(name, (metadata.len(), last_used));
+            }
+        }
+
+        Ok(Self {
+            dir,
+            entries,
+            total_bytes,
+        })
+    }
+
+    /// 读取缓存的缩略图，并刷新最近使用时间
+    fn get(&mut self, key: &str) -> Option<Vec<u8>> {
+        self.entries.get(key)?;
+        let path = self.dir.join(key);
+
+        match fs::read(&path) {
+            Ok(data) => {
+                let now = SystemTime::now();
+                if let Some(entry) = self.entries.get_mut(key) {
+                    entry.1 = now;
+                }
+                // 文件修改时间即最近使用时间，重启后 LRU 顺序仍然有效
+                if let Ok(file) = fs::File::options().write(true).open(&path) {
+                    let _ = file.set_modified(now);
+                }
+                Some(data)
+            }
+            Err(_) => {
+                self.remove(key);
+                None
+            }
+        }
+    }
+
+    /// 写入缩略图（临时文件 + 重命名），超出上限时按 LRU 移出索引，返回需要删除的文件
+    fn put(&mut self, key: &str, data: &[u8]) -> Result<Vec<PathBuf>, ComicError> {
+        let path = self.dir.join(key);
+        let tmp_path = self.dir.join(format!("{}.tmp", key));
+        fs::write(&tmp_path, data).map_err(|e| ComicError::io(&tmp_path, e))?;
+        fs::rename(&tmp_path, &path).map_err(|e| ComicError::io(&path, e))?;
+
+        if let Some((old_size, _)) = self.entries.insert(key.to_string(), (data.len() as u64, SystemTime::now())) {
+            self.total_bytes -= old_size;
+        }
+        self.total_bytes += data.len() as u64;
+
+        if self.total_bytes > MAX_CACHE_BYTES {
+            return Ok(self.evict());
+        }
+        Ok(Vec::new())
+    }
+
+    /// 从索引中移除，返回对应的缓存文件
+    fn forget(&mut self, key: &str) -> Option<PathBuf> {
+        let (size, _) = self.entries.remove(key)?;
+        self.total_bytes -= size;
+        Some(self.dir.join(key))
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(path) = self.forget(key) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// 按最近使用时间从旧到新移出索引，直到低于清理目标；返回被移出的文件，由调用方在锁外删除
+    fn evict(&mut self) -> Vec<PathBuf> {
+        let mut by_age: Vec<(String, SystemTime)> =
+            self.entries.iter().map(|(key, (_, used))| (key.clone(), *used)).collect();
+        by_age.sort_by_key(|(_, used)| *used);
+
+        let mut evicted = Vec::new();
+        for (key, _) in by_age {
+            if self.total_bytes <= EVICT_TARGET_BYTES {
+                break;
+            }
+            evicted.extend(self.forget(&key));
+        }
+        evicted
+    }
+}
+
+/// 缓存键：漫画路径 + 修改时间 + 大小 + 页面 + 尺寸
+/// 漫画文件变化后 mtime/size 改变，旧缓存自然失效并最终被 LRU 清理；
+/// 缓存文件跨版本保留，因此使用 SHA-256 而不是每个 Rust 版本可能不同的 DefaultHasher
+fn cache_key(comic_path: &str, page: Option<usize>, max_size: u32) -> Result<String, ComicError> {
+    let stamp = comic_stamp(comic_path).ok_or_else(|| ComicError::not_found(comic_path))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(comic_path.as_bytes());
+    hasher.update([0]);
+    hasher.update(stamp.size.to_le_bytes());
+    hasher.update(stamp.mtime.to_le_bytes());
+    let digest = hasher.finalize();
+    let hash: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+
+    let kind = match page {
+        Some(index) => format!("p{}", index),
+        None => "cover".to_string(),
+    };
+    Ok(format!("{}-{}-{}.jpg", hash, kind, max_size))
+}
+
+/// 解码页面并生成缩略图；PDF 直接按缩略图宽度渲染，避免先渲染大图
+fn generate_thumbnail(
+    comic_path: &str,
+    page_index: usize,
+    max_size: u32,
+    sources: &SourceCache,
+) -> Result<Vec<u8>, ComicError> {
+    let buffer = match ArchiveKind::from_path(Path::new(comic_path)) {
+        Some(ArchiveKind::Pdf) => render_page(comic_path, page_index, Some(max_size), None, sources)?,
+        _ => read_page(comic_path, page_index, sources)?,
+    };
+    make_thumbnail(&buffer, max_size, THUMBNAIL_QUALITY)
+}
+
+/// 获取缩略图（page 为 None 时为封面），优先读取磁盘缓存
+fn get_thumbnail(
+    app: &AppHandle,
+    cache: &ThumbnailCache,
+    sources: &SourceCache,
+    comic_path: &str,
+    page: Option<usize>,
+    max_size: u32,
+) -> Result<Vec<u8>, ComicError> {
+    let max_size = max_size.clamp(1, MAX_THUMBNAIL_SIZE);
+    let key = cache_key(comic_path, page, max_size)?;
+
+    {
+        let mut guard = cache.0.lock()?;
+        if guard.is_none() {
+            let cache_dir = app
+                .path()
+                .app_cache_dir()
+                .map_err(|e| ComicError::Io {
+                    path: None,
+                    source: e.to_string(),
+                })?;
+            *guard = Some(ThumbnailIndex::load(cache_dir.join(THUMBNAIL_DIR))?);
+        }
+        if let Some(data) = guard.as_mut().unwrap().get(&key) {
+            return Ok(data);
+        }
+    }
+
+    // 生成期间不持有缓存锁
+    let page_index = match page {
+        Some(index) => index,
+        None => get_cover_page_index(comic_path, sources)?,
+    };
+    let data = generate_thumbnail(comic_path, page_index, max_size, sources)?;
+
+    let evicted = {
+        let mut guard = cache.0.lock()?;
+        match guard.as_mut().map(|index| index.put(&key, &data)) {
+            Some(Ok(evicted)) => evicted,
+            // 写缓存失败不影响返回结果
+            Some(Err(e)) => {
+                eprintln!("{}", tr(Msg::Warning, &[&e]));
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    };
+    // 删除文件不持有缓存锁；已移出索引的文件即使被并发写回，也只会在下次读取失败时重新生成
+    for path in evicted {
+        let _ = fs::remove_file(path);
+    }
+
+    Ok(data)
+}
+
+/// 获取漫画封面缩略图
+pub fn get_cover_thumbnail(
+    app: &AppHandle,
+    cache: &ThumbnailCache,
+    sources: &SourceCache,
+    comic_path: &str,
+    max_size: u32,
+) -> Result<Vec<u8>, ComicError> {
+    get_thumbnail(app, cache, sources, comic_path, None, max_size)
+}
+
+/// 获取页面缩略图
+pub fn get_page_thumbnail(
+    app: &AppHandle,
+    cache: &ThumbnailCache,
+    sources: &SourceCache,
+    comic_path: &str,
+    page_index: usize,
+    max_size: u32,
+) -> Result<Vec<u8>, ComicError> {
+    get_thumbnail(app, cache, sources, comic_path, Some(page_index), max_size)
+}
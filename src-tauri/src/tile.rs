@@ -0,0 +1,295 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageEncoder};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use crate::comic_source::{get_page_dimensions, get_page_info, read_page_entry, SourceCache};
+use crate::error::ComicError;
+use crate::page_cache::PageKey;
+
+/// 默认分块高度（像素）
+const DEFAULT_TILE_HEIGHT: u32 = 2048;
+
+/// 分块的默认 JPEG 质量
+const DEFAULT_TILE_QUALITY: u8 = 85;
+
+/// 最多保留的已解码图片数（超长条漫解码后可达数百 MB，只保留最近几张）
+const DECODED_CACHE_SIZE: usize = 2;
+
+/// 分块输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TileFormat {
+    #[default]
+    Jpeg,
+    /// 无损，保留透明通道
+    Png,
+    /// 无损 WebP，保留透明通道
+    Webp,
+}
+
+/// 分块输出选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TileOptions {
+    #[serde(default)]
+    pub format: TileFormat,
+    /// JPEG 质量（1-100），其他格式忽略
+    #[serde(default)]
+    pub quality: Option<u8>,
+    /// 图片不需要分块时直接返回原始数据，不重新编码
+    #[serde(default)]
+    pub passthrough: bool,
+}
+
+/// 分块位置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileRect {
+    pub index: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 分块布局：前端先获取布局占位，再按需请求各分块
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileLayout {
+    pub width: u32,
+    pub height: u32,
+    #[serde(rename = "tileHeight")]
+    pub tile_height: u32,
+    pub tiles: Vec<TileRect>,
+}
+
+/// 已解码图片缓存：同一张图的各分块共用一次解码
+pub struct DecodedImageCache {
+    images: Mutex<LruCache<PageKey, Arc<DynamicImage>>>,
+    /// 正在解码的页面：前端并发请求同一张图的多个分块时只解码一次，不同页面互不阻塞
+    in_flight: Mutex<HashMap<PageKey, Arc<Mutex<()>>>>,
+}
+
+impl Default for DecodedImageCache {
+    fn default() -> Self {
+        Self {
+            images: Mutex::new(LruCache::new(NonZeroUsize::new(DECODED_CACHE_SIZE).unwrap())),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl DecodedImageCache {
+    fn get(&self, key: &PageKey) -> Option<Arc<DynamicImage>> {
+        self.images.lock().ok()?.get(key).cloned()
+    }
+
+    fn insert(&self, key: PageKey, image: Arc<DynamicImage>) {
+        if let Ok(mut images) = self.images.lock() {
+            images.put(key, image);
+        }
+    }
+
+    /// 页面的解码锁，同一页面的并发请求共用一把
+    fn decode_lock(&self, key: &PageKey) -> Result<Arc<Mutex<()>>, ComicError> {
+        Ok(self.in_flight.lock()?.entry(key.clone()).or_default().clone())
+    }
+
+    /// 解码结束后移除解码锁；仍在等待的请求持有各自的引用，拿到锁后会命中缓存
+    fn release_decode_lock(&self, key: &PageKey, lock: &Arc<Mutex<()>>) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            if in_flight.get(key).is_some_and(|current| Arc::ptr_eq(current, lock)) {
+                in_flight.remove(key);
+            }
+        }
+    }
+}
+
+/// 计算分块布局；不够高的图片（不超过两块高度）作为单个分块
+fn build_layout(width: u32, height: u32, tile_height: u32) -> TileLayout {
+    let mut tiles = Vec::new();
+
+    if height <= tile_height.saturating_mul(2) {
+        tiles.push(TileRect {
+            index: 0,
+            x: 0,
+            y: 0,
+            width,
+            height,
+        });
+    } else {
+        let mut y = 0;
+        while y < height {
+            let current_height = tile_height.min(height - y);
+            tiles.push(TileRect {
+                index: tiles.len(),
+                x: 0,
+                y,
+                width,
+                height: current_height,
+            });
+            y += current_height;
+        }
+    }
+
+    TileLayout {
+        width,
+        height,
+        tile_height,
+        tiles,
+    }
+}
+
+/// 分块高度，未指定时使用默认值；高度为 0 时无法分块
+fn resolve_tile_height(tile_height: Option<u32>) -> Result<u32, ComicError> {
+    match tile_height.unwrap_or(DEFAULT_TILE_HEIGHT) {
+        0 => Err(ComicError::InvalidTileHeight { height: 0 }),
+        height => Ok(height),
+    }
+}
+
+/// 获取页面的分块布局（只解析文件头，不解码图片）
+pub fn get_tile_layout(
+    comic_path: &str,
+    page_index: usize,
+    tile_height: Option<u32>,
+    sources: &SourceCache,
+) -> Result<TileLayout, ComicError> {
+    let tile_height = resolve_tile_height(tile_height)?;
+    let (width, height) = get_page_dimensions(comic_path, page_index, sources)?;
+    Ok(build_layout(width, height, tile_height))
+}
+
+/// 获取已解码的页面图片，优先使用缓存
+fn decoded_page(
+    comic_path: &str,
+    page_index: usize,
+    sources: &SourceCache,
+    decoded: &DecodedImageCache,
+) -> Result<Arc<DynamicImage>, ComicError> {
+    let page = get_page_info(comic_path, page_index, sources)?;
+    let key = PageKey::new(comic_path, &page.path);
+
+    if let Some(image) = decoded.get(&key) {
+        return Ok(image);
+    }
+
+    let lock = decoded.decode_lock(&key)?;
+    let result = (|| {
+        let _guard = lock.lock()?;
+
+        // 等待期间可能已由其他请求解码完成
+        if let Some(image) = decoded.get(&key) {
+            return Ok(image);
+        }
+
+        let (_, buffer) = read_page_entry(comic_path, page_index, sources)?;
+        let image = Arc::new(image::load_from_memory(&buffer).map_err(ComicError::decode)?);
+        decoded.insert(key.clone(), image.clone());
+        Ok(image)
+    })();
+    decoded.release_decode_lock(&key, &lock);
+    result
+}
+
+/// 按选项编码分块
+fn encode_tile(tile: &DynamicImage, options: &TileOptions) -> Result<Vec<u8>, ComicError> {
+    let mut buffer = Vec::new();
+
+    match options.format {
+        TileFormat::Jpeg => {
+            // JPEG 不支持透明通道，先转换为 RGB
+            let rgb = tile.to_rgb8();
+            let quality = options.quality.unwrap_or(DEFAULT_TILE_QUALITY).clamp(1, 100);
+            JpegEncoder::new_with_quality(&mut buffer, quality)
+                .encode_image(&rgb)
+                .map_err(ComicError::encode)?;
+        }
+        TileFormat::Png => {
+            let rgba = tile.to_rgba8();
+            PngEncoder::new(&mut buffer)
+                .write_image(&rgba, rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
+                .map_err(ComicError::encode)?;
+        }
+        TileFormat::Webp => {
+            let rgba = tile.to_rgba8();
+            WebPEncoder::new_lossless(&mut buffer)
+                .encode(&rgba, rgba.width(), rgba.height(), image::ExtendedColorType::Rgba8)
+                .map_err(ComicError::encode)?;
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// 获取单个分块，文件夹和压缩包中的页面都适用
+pub fn get_tile(
+    comic_path: &str,
+    page_index: usize,
+    tile_index: usize,
+    tile_height: Option<u32>,
+    options: &TileOptions,
+    sources: &SourceCache,
+    decoded: &DecodedImageCache,
+) -> Result<Vec<u8>, ComicError> {
+    let tile_height = resolve_tile_height(tile_height)?;
+
+    // 不需要分块的图片直接返回原始数据，无需解码
+    if options.passthrough && tile_index == 0 {
+        let layout = get_tile_layout(comic_path, page_index, Some(tile_height), sources)?;
+        if layout.tiles.len() == 1 {
+            let (_, buffer) = read_page_entry(comic_path, page_index, sources)?;
+            return Ok(buffer);
+        }
+    }
+
+    let image = decoded_page(comic_path, page_index, sources, decoded)?;
+
+    let layout = build_layout(image.width(), image.height(), tile_height);
+    let tile = layout
+        .tiles
+        .get(tile_index)
+        .ok_or_else(|| ComicError::TileOutOfRange {
+            path: comic_path.to_string(),
+            index: tile_index,
+        })?;
+
+    encode_tile(&image.crop_imm(tile.x, tile.y, tile.width, tile.height), options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_layout_does_not_overflow_with_huge_tile_height() {
+        let layout = build_layout(800, 5000, u32::MAX);
+        assert_eq!(layout.tiles.len(), 1);
+        assert_eq!(layout.tiles[0].height, 5000);
+    }
+
+    #[test]
+    fn resolve_tile_height_rejects_zero() {
+        assert_eq!(resolve_tile_height(Some(0)), Err(ComicError::InvalidTileHeight { height: 0 }));
+        assert_eq!(resolve_tile_height(None), Ok(DEFAULT_TILE_HEIGHT));
+    }
+
+    #[test]
+    fn decode_locks_are_per_page() {
+        let cache = DecodedImageCache::default();
+        let a = PageKey::new("/missing/comic.cbz", "001.jpg");
+        let b = PageKey::new("/missing/comic.cbz", "002.jpg");
+
+        let lock_a = cache.decode_lock(&a).unwrap();
+        let _held = lock_a.lock().unwrap();
+        assert!(Arc::ptr_eq(&lock_a, &cache.decode_lock(&a).unwrap()));
+        // 其他页面的解码锁不受影响
+        assert!(cache.decode_lock(&b).unwrap().try_lock().is_ok());
+
+        cache.release_decode_lock(&a, &lock_a);
+        assert!(!Arc::ptr_eq(&lock_a, &cache.decode_lock(&a).unwrap()));
+    }
+}
@@ -0,0 +1,272 @@
+use notify_debouncer_full::notify::event::{EventKind, ModifyKind};
+use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::ComicError;
+use crate::i18n::{tr, Msg};
+use crate::file_system::{is_image_file, ArchiveKind, FileNode};
+use crate::library::{get_library_entry, remove_library_entries, scan_library, LibraryDb, LibraryEntry};
+use crate::storage::{get_opened_directories, AppDataCache};
+
+/// 事件防抖时间：下载工具写入整个章节期间只推送一次
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 检查根目录是否被重命名/卸载或重新出现的间隔
+const ROOT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 重新扫描变化节点时的深度，与前端默认扫描深度一致
+const WATCH_SCAN_DEPTH: u32 = 5;
+
+pub const EVENT_ADDED: &str = "library://added";
+pub const EVENT_REMOVED: &str = "library://removed";
+pub const EVENT_CHANGED: &str = "library://changed";
+
+/// 打开的目录的文件系统监听器
+pub struct LibraryWatcher(pub Mutex<Option<WatcherState>>);
+
+impl Default for LibraryWatcher {
+    fn default() -> Self {
+        Self(Mutex::new(None))
+    }
+}
+
+pub struct WatcherState {
+    debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+    /// 根目录 -> 是否正在监听（目录不存在时暂停，重新出现后恢复）
+    roots: HashMap<String, bool>,
+}
+
+impl WatcherState {
+    fn watch(&mut self, root: &str) -> bool {
+        let path = Path::new(root);
+        if !path.is_dir() {
+            return false;
+        }
+
+        match self.debouncer.watcher().watch(path, RecursiveMode::Recursive) {
+            Ok(()) => {
+                self.debouncer.cache().add_root(path, RecursiveMode::Recursive);
+                true
+            }
+            Err(e) => {
+                eprintln!("{}", tr(Msg::WarnWatchFailed, &[&root, &e]));
+                false
+            }
+        }
+    }
+
+    fn unwatch(&mut self, root: &str) {
+        // 目录已被删除或卸载时 unwatch 会失败，忽略即可
+        let _ = self.debouncer.watcher().unwatch(Path::new(root));
+        self.debouncer.cache().remove_root(root);
+    }
+}
+
+/// 受影响的库节点：漫画文件本身，或图片所在的目录
+fn affected_node(path: &Path) -> Option<PathBuf> {
+    if path.is_dir() || ArchiveKind::from_path(path).is_some() {
+        return Some(path.to_path_buf());
+    }
+    if is_image_file(path) {
+        return path.parent().map(|p| p.to_path_buf());
+    }
+    // 已删除的路径无法判断类型，没有扩展名的视为目录
+    if !path.exists() && path.extension().is_none() {
+        return Some(path.to_path_buf());
+    }
+    None
+}
+
+/// 由库条目构造文件节点（用于已删除的路径）
+fn node_from_entry(path: &str, entry: Option<LibraryEntry>) -> FileNode {
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+
+    match entry {
+        Some(entry) => FileNode {
+            name: entry.name,
+            path: entry.path,
+            is_directory: entry.is_directory,
+            is_comic: entry.is_comic,
+            archive_kind: entry.archive_kind,
+            image_count: entry.page_count,
+            children: None,
+        },
+        None => {
+            let archive_kind = ArchiveKind::from_path(Path::new(path));
+            FileNode {
+                name,
+                path: path.to_string(),
+                is_directory: archive_kind.is_none(),
+                is_comic: archive_kind.is_some(),
+                archive_kind,
+                image_count: 0,
+                children: None,
+            }
+        }
+    }
+}
+
+/// 重新检查受影响的节点并推送事件：新出现的为 added，已知的为 changed，不存在的为 removed
+fn emit_node_updates(app: &AppHandle, nodes: BTreeSet<PathBuf>) {
+    let db = app.state::<LibraryDb>();
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut removed = Vec::new();
+
+    for node in nodes {
+        let path = node.to_string_lossy().to_string();
+
+        if node.exists() {
+            let known = matches!(get_library_entry(app, &db, &path), Ok(Some(_)));
+            match scan_library(app, &db, &path, WATCH_SCAN_DEPTH) {
+                Ok(file_node) if known => changed.push(file_node),
+                Ok(file_node) => added.push(file_node),
+                Err(e) => eprintln!("{}", tr(Msg::WarnScanFailed, &[&path, &e])),
+            }
+        } else {
+            match remove_library_entries(app, &db, &path) {
+                Ok(entry) => removed.push(node_from_entry(&path, entry)),
+                Err(e) => eprintln!("{}", tr(Msg::WarnLibraryUpdateFailed, &[&path, &e])),
+            }
+        }
+    }
+
+    for (event, nodes) in [(EVENT_ADDED, added), (EVENT_CHANGED, changed), (EVENT_REMOVED, removed)] {
+        if !nodes.is_empty() {
+            if let Err(e) = app.emit(event, nodes) {
+                eprintln!("{}", tr(Msg::WarnEmitFailed, &[&event, &e]));
+            }
+        }
+    }
+}
+
+/// 处理一批防抖后的事件
+fn handle_events(app: &AppHandle, result: DebounceEventResult) {
+    let events = match result {
+        Ok(events) => events,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{}", tr(Msg::WarnWatcherError, &[&e]));
+            }
+            return;
+        }
+    };
+
+    let mut nodes = BTreeSet::new();
+    for event in events {
+        // 只读访问不影响漫画库
+        if matches!(event.kind, EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_))) {
+            continue;
+        }
+        // 重命名事件同时包含旧路径和新路径，分别按删除和新增处理
+        for path in &event.paths {
+            if let Some(node) = affected_node(path) {
+                nodes.insert(node);
+            }
+        }
+    }
+
+    if !nodes.is_empty() {
+        emit_node_updates(app, nodes);
+    }
+}
+
+/// 检查根目录状态：被重命名或卸载的暂停监听并推送 removed，重新出现的恢复监听并推送 added
+fn check_roots(app: &AppHandle) {
+    let watcher = app.state::<LibraryWatcher>();
+    let mut reappeared = BTreeSet::new();
+    let mut vanished = BTreeSet::new();
+
+    {
+        let Ok(mut guard) = watcher.0.lock() else {
+            return;
+        };
+        let Some(state) = guard.as_mut() else {
+            return;
+        };
+
+        let roots: Vec<(String, bool)> = state.roots.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        for (root, watching) in roots {
+            let exists = Path::new(&root).is_dir();
+            if watching && !exists {
+                state.unwatch(&root);
+                state.roots.insert(root.clone(), false);
+                vanished.insert(PathBuf::from(root));
+            } else if !watching && exists && state.watch(&root) {
+                state.roots.insert(root.clone(), true);
+                reappeared.insert(PathBuf::from(root));
+            }
+        }
+    }
+
+    // 推送事件时不持有监听器锁
+    vanished.append(&mut reappeared);
+    if !vanished.is_empty() {
+        emit_node_updates(app, vanished);
+    }
+}
+
+/// 使监听的根目录与 AppData.opened_directories 保持一致
+pub fn sync_watched_directories(app: &AppHandle) -> Result<(), ComicError> {
+    let directories = get_opened_directories(app, &app.state::<AppDataCache>())?;
+    let watcher = app.state::<LibraryWatcher>();
+    let mut guard = watcher.0.lock()?;
+    let Some(state) = guard.as_mut() else {
+        return Ok(());
+    };
+
+    let wanted: BTreeSet<String> = directories.into_iter().map(|d| d.path).collect();
+
+    let stale: Vec<String> = state.roots.keys().filter(|root| !wanted.contains(*root)).cloned().collect();
+    for root in stale {
+        if state.roots.remove(&root) == Some(true) {
+            state.unwatch(&root);
+        }
+    }
+
+    for root in wanted {
+        if !state.roots.contains_key(&root) {
+            let watching = state.watch(&root);
+            state.roots.insert(root, watching);
+        }
+    }
+
+    Ok(())
+}
+
+/// 启动目录监听：创建防抖监听器、监听已打开的目录，并定期检查根目录状态
+pub fn start_library_watcher(app: &AppHandle) -> Result<(), ComicError> {
+    let handler_app = app.clone();
+    let debouncer = new_debouncer(DEBOUNCE_TIMEOUT, None, move |result: DebounceEventResult| {
+        handle_events(&handler_app, result);
+    })
+    .map_err(|e| ComicError::Watcher { source: e.to_string() })?;
+
+    {
+        let watcher = app.state::<LibraryWatcher>();
+        let mut guard = watcher.0.lock()?;
+        *guard = Some(WatcherState {
+            debouncer,
+            roots: HashMap::new(),
+        });
+    }
+
+    sync_watched_directories(app)?;
+
+    let check_app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(ROOT_CHECK_INTERVAL);
+        check_roots(&check_app);
+    });
+
+    Ok(())
+}
@@ -1,210 +1,220 @@
-use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{BufReader, Read};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
-use std::sync::Mutex;
-use zip::ZipArchive;
-use lru::LruCache;
-use std::num::NonZeroUsize;
+use zip::write::SimpleFileOptions;
+use zip::result::ZipError;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::comic_info::{
+    apply_comic_info, is_comic_info_file, parse_comic_info, serialize_comic_info, ComicInfo, COMIC_INFO_FILE,
+};
+use crate::comic_source::{build_page_list, ComicSource, PageInfo};
+use crate::error::ComicError;
+use crate::i18n::{tr, Msg};
+use crate::file_system::ArchiveKind;
+use crate::image_handler::get_image_dimensions_from_reader;
+
+/// 打开 ZIP 文件
+pub fn open_zip_archive(zip_path: &str) -> Result<ZipArchive<BufReader<File>>, ComicError> {
+    let path = Path::new(zip_path);
 
-use crate::file_system::is_image_file;
+    if !path.exists() {
+        return Err(ComicError::not_found(zip_path));
+    }
 
-/// ZIP 句柄缓存：使用 LRU 缓存保留最近使用的 5 个 ZIP
-pub struct ZipCache(pub Mutex<LruCache<String, ZipArchive<BufReader<File>>>>);
+    let file = File::open(path).map_err(|e| ComicError::io(zip_path, e))?;
+    ZipArchive::new(BufReader::new(file)).map_err(|e| ComicError::corrupt(zip_path, e))
+}
 
-impl Default for ZipCache {
-    fn default() -> Self {
-        Self(Mutex::new(LruCache::new(NonZeroUsize::new(5).unwrap())))
+/// 条目查找错误：找不到条目与压缩包损坏分开报告
+fn entry_error(zip_path: &str, name: &str, e: ZipError) -> ComicError {
+    match e {
+        ZipError::FileNotFound => ComicError::entry_not_found(zip_path, name),
+        e => ComicError::corrupt(zip_path, e),
     }
 }
 
-/// ZIP 内图片信息
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ZipImageInfo {
-    pub index: usize,
-    pub name: String,
-    pub path: String,
-    pub size: u64,
-}
+/// 读取 ZIP 中指定条目的二进制数据
+pub fn read_zip_entry(
+    archive: &mut ZipArchive<BufReader<File>>,
+    zip_path: &str,
+    name: &str,
+) -> Result<Vec<u8>, ComicError> {
+    let mut zip_file = archive.by_name(name).map_err(|e| entry_error(zip_path, name, e))?;
 
-/// 获取 ZIP 文件中的图片列表
-pub fn get_zip_image_list(zip_path: &str) -> Result<Vec<ZipImageInfo>, String> {
-    let path = Path::new(zip_path);
-    
-    if !path.exists() {
-        return Err(format!("ZIP 文件不存在: {}", zip_path));
-    }
+    let mut buffer = Vec::with_capacity(zip_file.size() as usize);
+    zip_file
+        .read_to_end(&mut buffer)
+        .map_err(|e| ComicError::corrupt(zip_path, e))?;
 
-    let file = File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
-    let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|e| format!("无法读取 ZIP: {}", e))?;
+    Ok(buffer)
+}
 
-    let mut images: Vec<ZipImageInfo> = Vec::new();
+/// 只读取 ZIP 条目开头部分解析图片尺寸
+pub fn read_zip_entry_dimensions(
+    archive: &mut ZipArchive<BufReader<File>>,
+    zip_path: &str,
+    name: &str,
+) -> Result<(u32, u32), ComicError> {
+    let mut zip_file = archive.by_name(name).map_err(|e| entry_error(zip_path, name, e))?;
+    get_image_dimensions_from_reader(&mut zip_file).map_err(|e| e.with_path(zip_path))
+}
 
-    for i in 0..archive.len() {
-        if let Ok(file) = archive.by_index(i) {
-            let file_path = file.name().to_string();
-            let file_path_obj = Path::new(&file_path);
-            
-            // 跳过目录和隐藏文件
-            if file.is_dir() {
-                continue;
-            }
-            
-            if let Some(name) = file_path_obj.file_name() {
-                let name_str = name.to_string_lossy().to_string();
-                if name_str.starts_with('.') || name_str.starts_with("__MACOSX") {
-                    continue;
-                }
-            }
+/// 将 ComicInfo 写入 ZIP 根目录
+/// 其他条目按原始压缩数据复制，不重新压缩；先写入临时文件再重命名，保证原文件不会被写坏
+pub fn write_zip_comic_info(zip_path: &str, info: &ComicInfo) -> Result<(), ComicError> {
+    let mut archive = open_zip_archive(zip_path)?;
+
+    // 根目录下已有的 ComicInfo.xml，保留其中未维护的字段
+    let existing = archive
+        .file_names()
+        .find(|name| !name.contains('/') && is_comic_info_file(Path::new(name)))
+        .map(|name| name.to_string());
+    let original = match &existing {
+        Some(name) => String::from_utf8(read_zip_entry(&mut archive, zip_path, name)?).ok(),
+        None => None,
+    };
+    let xml = serialize_comic_info(info, original.as_deref());
 
-            // 检查是否为图片文件
-            if is_image_file(file_path_obj) {
-                let name = file_path_obj
-                    .file_name()
-                    .map(|n| n.to_string_lossy().to_string())
-                    .unwrap_or_else(|| file_path.clone());
-
-                images.push(ZipImageInfo {
-                    index: i,
-                    name,
-                    path: file_path,
-                    size: file.size(),
-                });
+    let path = Path::new(zip_path);
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!("{}.tmp", file_name));
+
+    let result = (|| -> Result<(), ComicError> {
+        let tmp_file = File::create(&tmp_path).map_err(|e| ComicError::io(&tmp_path, e))?;
+        let mut writer = ZipWriter::new(BufWriter::new(tmp_file));
+
+        for i in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(i)
+                .map_err(|e| ComicError::corrupt(zip_path, e))?;
+            if existing.as_deref() == Some(entry.name()) {
+                continue;
             }
+            writer
+                .raw_copy_file(entry)
+                .map_err(|e| ComicError::io(&tmp_path, e))?;
         }
-    }
-
-    // 自然排序
-    images.sort_by(|a, b| natord::compare(&a.name, &b.name));
 
-    // 重新分配索引
-    for (i, img) in images.iter_mut().enumerate() {
-        img.index = i;
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer
+            .start_file(COMIC_INFO_FILE, options)
+            .map_err(|e| ComicError::io(&tmp_path, e))?;
+        writer
+            .write_all(xml.as_bytes())
+            .map_err(|e| ComicError::io(&tmp_path, e))?;
+
+        let tmp_file = writer
+            .finish()
+            .map_err(|e| ComicError::io(&tmp_path, e))?
+            .into_inner()
+            .map_err(|e| ComicError::io(&tmp_path, e))?;
+
+        // 重命名前先落盘，避免崩溃或断电后留下不完整的漫画文件
+        tmp_file.sync_all().map_err(|e| ComicError::io(&tmp_path, e))
+    })();
+
+    // 释放原文件句柄后再替换（Windows 下不能重命名到已打开的文件）
+    drop(archive);
+
+    match result {
+        Ok(()) => fs::rename(&tmp_path, path).map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+            ComicError::io(path, e)
+        }),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
     }
-
-    Ok(images)
 }
 
-/// 从 ZIP 文件中读取指定图片的数据（Base64），使用 LRU 缓存
-pub fn read_zip_image(zip_path: &str, image_path: &str, cache: &ZipCache) -> Result<String, String> {
-    let path = Path::new(zip_path);
+/// ZIP 漫画来源：保留打开的 ZIP 句柄，支持随机读取
+pub struct ZipSource {
+    path: String,
+    archive: ZipArchive<BufReader<File>>,
+    pages: Vec<PageInfo>,
+    comic_info: Option<ComicInfo>,
+}
 
-    if !path.exists() {
-        return Err(format!("ZIP 文件不存在: {}", zip_path));
-    }
+impl ZipSource {
+    /// 打开 ZIP 文件并读取图片列表
+    pub fn open(zip_path: &str) -> Result<Self, ComicError> {
+        let mut archive = open_zip_archive(zip_path)?;
 
-    let mut guard = cache.0.lock().map_err(|e| format!("锁获取失败: {}", e))?;
+        let mut entries = Vec::new();
+        let mut comic_info_entry: Option<String> = None;
 
-    // 检查 LRU 缓存中是否存在，不存在则打开并插入
-    if !guard.contains(&zip_path.to_string()) {
-        let file = File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
-        let archive = ZipArchive::new(BufReader::new(file))
-            .map_err(|e| format!("无法读取 ZIP: {}", e))?;
-        guard.put(zip_path.to_string(), archive);
-    }
+        for i in 0..archive.len() {
+            if let Ok(file) = archive.by_index(i) {
+                // 跳过目录
+                if file.is_dir() {
+                    continue;
+                }
 
-    // 从 LRU 缓存获取（会自动更新访问顺序）
-    let archive = guard.get_mut(&zip_path.to_string()).unwrap();
+                // 记录 ComicInfo.xml，优先使用根目录下的
+                if is_comic_info_file(Path::new(file.name()))
+                    && !matches!(&comic_info_entry, Some(name) if !name.contains('/'))
+                {
+                    comic_info_entry = Some(file.name().to_string());
+                }
 
-    let mut zip_file = archive
-        .by_name(image_path)
-        .map_err(|e| format!("无法找到图片: {}", e))?;
+                entries.push((file.name().to_string(), file.size()));
+            }
+        }
 
-    let mut buffer = Vec::new();
-    zip_file
-        .read_to_end(&mut buffer)
-        .map_err(|e| format!("无法读取图片数据: {}", e))?;
+        let comic_info = comic_info_entry.and_then(|name| {
+            let parsed = read_zip_entry(&mut archive, zip_path, &name)
+                .and_then(|buffer| String::from_utf8(buffer).map_err(ComicError::metadata))
+                .and_then(|text| parse_comic_info(&text));
+            match parsed {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    eprintln!("{}", tr(Msg::WarnReadFailed, &[&name, &e]));
+                    None
+                }
+            }
+        });
 
-    let mime_type = get_mime_type(image_path);
-    let base64_data = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &buffer);
+        let mut pages = build_page_list(entries);
+        if let Some(info) = &comic_info {
+            pages = apply_comic_info(pages, info);
+        }
 
-    Ok(format!("data:{};base64,{}", mime_type, base64_data))
+        Ok(Self {
+            path: zip_path.to_string(),
+            archive,
+            pages,
+            comic_info,
+        })
+    }
 }
 
-/// 从 ZIP 文件中读取指定图片的二进制数据（用于 Blob URL）
-pub fn read_zip_image_bytes(zip_path: &str, image_path: &str, cache: &ZipCache) -> Result<Vec<u8>, String> {
-    let path = Path::new(zip_path);
-
-    if !path.exists() {
-        return Err(format!("ZIP 文件不存在: {}", zip_path));
+impl ComicSource for ZipSource {
+    fn path(&self) -> &str {
+        &self.path
     }
 
-    let mut guard = cache.0.lock().map_err(|e| format!("锁获取失败: {}", e))?;
-
-    // 检查 LRU 缓存中是否存在，不存在则打开并插入
-    if !guard.contains(&zip_path.to_string()) {
-        let file = File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
-        let archive = ZipArchive::new(BufReader::new(file))
-            .map_err(|e| format!("无法读取 ZIP: {}", e))?;
-        guard.put(zip_path.to_string(), archive);
+    fn archive_kind(&self) -> Option<ArchiveKind> {
+        Some(ArchiveKind::Zip)
     }
 
-    // 从 LRU 缓存获取（会自动更新访问顺序）
-    let archive = guard.get_mut(&zip_path.to_string()).unwrap();
-
-    let mut zip_file = archive
-        .by_name(image_path)
-        .map_err(|e| format!("无法找到图片: {}", e))?;
-
-    let mut buffer = Vec::new();
-    zip_file
-        .read_to_end(&mut buffer)
-        .map_err(|e| format!("无法读取图片数据: {}", e))?;
-
-    Ok(buffer)
-}
-
-/// 从 ZIP 文件中获取图片尺寸
-pub fn get_zip_image_dimensions(zip_path: &str, image_path: &str, cache: &ZipCache) -> Result<(u32, u32), String> {
-    let path = Path::new(zip_path);
-
-    if !path.exists() {
-        return Err(format!("ZIP 文件不存在: {}", zip_path));
+    fn pages(&self) -> &[PageInfo] {
+        &self.pages
     }
 
-    let mut guard = cache.0.lock().map_err(|e| format!("锁获取失败: {}", e))?;
-
-    // 检查 LRU 缓存中是否存在，不存在则打开并插入
-    if !guard.contains(&zip_path.to_string()) {
-        let file = File::open(path).map_err(|e| format!("无法打开文件: {}", e))?;
-        let archive = ZipArchive::new(BufReader::new(file))
-            .map_err(|e| format!("无法读取 ZIP: {}", e))?;
-        guard.put(zip_path.to_string(), archive);
+    fn read_page(&mut self, page: &PageInfo) -> Result<Vec<u8>, ComicError> {
+        read_zip_entry(&mut self.archive, &self.path, &page.path)
     }
 
-    // 从 LRU 缓存获取
-    let archive = guard.get_mut(&zip_path.to_string()).unwrap();
-
-    let mut zip_file = archive
-        .by_name(image_path)
-        .map_err(|e| format!("无法找到图片: {}", e))?;
-
-    let mut buffer = Vec::new();
-    zip_file
-        .read_to_end(&mut buffer)
-        .map_err(|e| format!("无法读取图片数据: {}", e))?;
-
-    // 使用 image crate 从内存中读取图片尺寸
-    let reader = image::ImageReader::new(std::io::Cursor::new(buffer))
-        .with_guessed_format()
-        .map_err(|e| format!("无法识别图片格式: {}", e))?;
-    
-    let dimensions = reader.into_dimensions()
-        .map_err(|e| format!("无法获取图片尺寸: {}", e))?;
-
-    Ok(dimensions)
-}
+    fn page_dimensions(&mut self, page: &PageInfo) -> Result<(u32, u32), ComicError> {
+        read_zip_entry_dimensions(&mut self.archive, &self.path, &page.path)
+    }
 
-/// 获取 MIME 类型
-fn get_mime_type(path: &str) -> &'static str {
-    let path = Path::new(path);
-    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("png") => "image/png",
-        Some("gif") => "image/gif",
-        Some("webp") => "image/webp",
-        Some("bmp") => "image/bmp",
-        Some("tiff") | Some("tif") => "image/tiff",
-        _ => "application/octet-stream",
+    fn comic_info(&self) -> Option<&ComicInfo> {
+        self.comic_info.as_ref()
     }
 }
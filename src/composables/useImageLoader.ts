@@ -1,5 +1,5 @@
-import { ref } from 'vue';
-import { useComicStore } from '../stores';
+import { ref, watch } from 'vue';
+import { useComicStore, useSettingsStore } from '../stores';
 
 const isDev = import.meta.env.DEV;
 
@@ -13,6 +13,17 @@ export function useImageLoader() {
     const lastPreloadCenter = ref<number>(-1);  // 上次预加载的中心图片索引
     
     const MAX_RETRY = 3;  // 最大重试次数
+
+    // 切换自动裁剪后，已显示的页面改用新地址重新加载
+    const settingsStore = useSettingsStore();
+    watch(() => settingsStore.settings.autoCrop, () => {
+        const urls = comicStore.refreshPageUrls();
+        for (const index of Object.keys(loadedImages.value).map(Number)) {
+            if (urls[index]) {
+                loadedImages.value[index] = urls[index];
+            }
+        }
+    });
     
     // 加载图片
     async function loadImage(index: number, totalImages: number) {
@@ -101,6 +112,9 @@ export function useImageLoader() {
         }
         
         if (isDev) console.log(`[懒加载] 加载队列:`, loadQueue);
+
+        // 让后端并行解压预加载范围内的页面，<img> 请求时直接从内存返回
+        comicStore.prefetchPages(startIndex, endIndex - startIndex + 1);
         
         // 在后台异步加载图片，不阻塞 UI
         (async () => {
@@ -1,7 +1,7 @@
 import { defineStore } from "pinia";
 import { ref } from "vue";
 import { invoke } from "@tauri-apps/api/core";
-import type { Bookmark, ReadingProgress } from "../types";
+import type { Bookmark, ReadingProgress, Spread, SpreadMode } from "../types";
 
 export const useBookmarkStore = defineStore("bookmark", () => {
   // 状态
@@ -122,6 +122,7 @@ export const useProgressStore = defineStore("progress", () => {
     scrollPosition: number,
     zoomMode?: string,
     customZoom?: number,
+    spreadMode?: SpreadMode,
   ) {
     const progress: ReadingProgress = {
       comicPath,
@@ -130,6 +131,8 @@ export const useProgressStore = defineStore("progress", () => {
       lastReadTime: Date.now(),
       zoomMode: zoomMode as ReadingProgress['zoomMode'],
       customZoom,
+      // 未指定时保留已保存的书本模式
+      spreadMode: spreadMode ?? progressMap.value[comicPath]?.spreadMode,
     };
 
     try {
@@ -166,11 +169,43 @@ export const useProgressStore = defineStore("progress", () => {
     }
   }
 
+  // 保存书本模式（随阅读进度一起记录）
+  async function saveSpreadMode(comicPath: string, spreadMode: SpreadMode) {
+    const existing = await getProgress(comicPath);
+    await saveProgress(
+      comicPath,
+      existing?.lastImageIndex ?? 0,
+      existing?.scrollPosition ?? 0,
+      existing?.zoomMode,
+      existing?.customZoom,
+      spreadMode,
+    );
+  }
+
+  // 获取书本模式的分组（single 模式每页单独一组，无需请求后端）
+  async function planSpreads(
+    comicPath: string,
+    spreadMode: SpreadMode,
+    pageCount: number,
+    rightToLeft?: boolean,
+  ): Promise<Spread[]> {
+    if (spreadMode === "single") {
+      return Array.from({ length: pageCount }, (_, i) => ({ pages: [i] }));
+    }
+    return invoke<Spread[]>("cmd_plan_spreads", {
+      comicPath,
+      offset: spreadMode === "double-offset",
+      rightToLeft: rightToLeft ?? null,
+    });
+  }
+
   return {
     // 状态
     progressMap,
     // 方法
     saveProgress,
     getProgress,
+    saveSpreadMode,
+    planSpreads,
   };
 });
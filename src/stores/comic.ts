@@ -1,7 +1,10 @@
 import { defineStore } from "pinia";
 import { ref, computed } from "vue";
-import { invoke } from "@tauri-apps/api/core";
-import type { FileNode, ComicInfo, ImageInfo, ZipImageInfo, OpenedDirectory } from "../types";
+import { invoke, convertFileSrc } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import { useSettingsStore } from "./settings";
+import { errorMessage } from "../types";
+import type { FileNode, ComicInfo, ComicInfoXml, ImageInfo, OpenedComic, OpenedDirectory, VirtualPage } from "../types";
 
 const isDev = import.meta.env.DEV;
 
@@ -13,6 +16,7 @@ export const useComicStore = defineStore("comic", () => {
   const loadingProgress = ref(0);
   const error = ref<string | null>(null);
   const imageLoadingStates = ref<Record<number, boolean>>({});  // 追踪图片加载状态
+  const dirs = ref<string[]>([]);  // 已保存的根目录路径，用于判断推送的节点是否为根节点
 
   // 计算属性
   const hasComic = computed(() => currentComic.value !== null);
@@ -41,10 +45,13 @@ export const useComicStore = defineStore("comic", () => {
       
       // 保存到持久化存储
       await saveOpenedDirectory(path);
+      if (!dirs.value.includes(tree.path)) {
+        dirs.value.push(tree.path);
+      }
       
       return tree;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
       throw e;
     } finally {
       isLoading.value = false;
@@ -57,6 +64,7 @@ export const useComicStore = defineStore("comic", () => {
     if (index >= 0) {
       fileTrees.value.splice(index, 1);
     }
+    dirs.value = dirs.value.filter(d => d !== path);
     
     // 从持久化存储中移除
     await removeOpenedDirectory(path);
@@ -100,128 +108,184 @@ export const useComicStore = defineStore("comic", () => {
     }
   }
 
-  // 打开漫画（文件夹）
-  async function openComicFromFolder(path: string, name: string) {
-    isLoading.value = true;
-    loadingProgress.value = 0;
-    error.value = null;
+  // 判断 childPath 是否为 parentPath 的直接子项（兼容 / 和 \\ 分隔符）
+  function isDirectChild(parentPath: string, childPath: string) {
+    if (!childPath.startsWith(parentPath)) return false;
+    return /^[\\/][^\\/]+$/.test(childPath.slice(parentPath.length));
+  }
 
-    try {
-      const imagePaths = await invoke<string[]>("cmd_get_directory_images", {
-        path,
-      });
+  // 在文件树中查找节点，返回所在数组和下标
+  function findNode(nodes: FileNode[], path: string): [FileNode[], number] | null {
+    for (let i = 0; i < nodes.length; i++) {
+      if (nodes[i].path === path) return [nodes, i];
+      const children = nodes[i].children;
+      if (children) {
+        const found = findNode(children, path);
+        if (found) return found;
+      }
+    }
+    return null;
+  }
 
-      const images: ImageInfo[] = imagePaths.map((imgPath, index) => ({
-        index,
-        name:
-          imgPath.split("/").pop() ||
-          imgPath.split("\\").pop() ||
-          `image_${index}`,
-        path: imgPath,
-      }));
+  // 插入或替换节点：已存在则替换，否则挂到父节点下（找不到父节点时忽略，根目录直接加入）
+  function upsertNode(node: FileNode) {
+    const found = findNode(fileTrees.value, node.path);
+    if (found) {
+      found[0][found[1]] = node;
+      return;
+    }
 
-      // 批量获取图片尺寸
-      if (isDev) console.log('[漫画加载] 开始获取图片尺寸...');
-      const dimensionsPromises = images.map(async (img) => {
-        try {
-          const [width, height] = await invoke<[number, number]>("cmd_get_image_dimensions", {
-            path: img.path,
-          });
-          img.width = width;
-          img.height = height;
-        } catch (e) {
-          console.warn(`获取图片 ${img.path} 尺寸失败:`, e);
-          // 使用默认尺寸
-          img.width = 800;
-          img.height = 1200;
-        }
+    const isRoot = dirs.value.includes(node.path);
+    if (isRoot) {
+      fileTrees.value.push(node);
+      return;
+    }
+
+    const parent = findParent(fileTrees.value, node.path);
+    if (parent) {
+      parent.children = [...(parent.children ?? []), node].sort((a, b) => {
+        if (a.isDirectory !== b.isDirectory) return a.isDirectory ? -1 : 1;
+        return a.name.localeCompare(b.name, undefined, { numeric: true });
       });
-      
-      await Promise.all(dimensionsPromises);
-      if (isDev) console.log('[漫画加载] 图片尺寸获取完成');
+    }
+  }
 
-      currentComic.value = {
-        path,
-        name,
-        isZip: false,
-        imageCount: images.length,
-        images,
-      };
+  // 查找直接父节点
+  function findParent(nodes: FileNode[], path: string): FileNode | null {
+    for (const node of nodes) {
+      if (isDirectChild(node.path, path)) return node;
+      if (node.children && path.startsWith(node.path)) {
+        const found = findParent(node.children, path);
+        if (found) return found;
+      }
+    }
+    return null;
+  }
 
-      return currentComic.value;
-    } catch (e) {
-      error.value = String(e);
-      throw e;
-    } finally {
-      isLoading.value = false;
+  // 移除节点
+  function removeNode(path: string) {
+    const found = findNode(fileTrees.value, path);
+    if (found) {
+      found[0].splice(found[1], 1);
     }
   }
 
-  // 打开漫画（ZIP）
-  async function openComicFromZip(path: string, name: string) {
+  let unlistenLibrary: UnlistenFn[] = [];
+
+  // 监听后端推送的目录变化（新增章节、删除、目录重命名或卸载）
+  async function startLibraryWatch() {
+    if (unlistenLibrary.length > 0) return;
+
+    const directories = await invoke<OpenedDirectory[]>("cmd_get_opened_directories");
+    dirs.value = directories.map(d => d.path);
+
+    unlistenLibrary = await Promise.all([
+      listen<FileNode[]>("library://added", (event) => {
+        event.payload.forEach(upsertNode);
+      }),
+      listen<FileNode[]>("library://changed", (event) => {
+        event.payload.forEach(upsertNode);
+      }),
+      listen<FileNode[]>("library://removed", (event) => {
+        event.payload.forEach(node => removeNode(node.path));
+      }),
+    ]);
+  }
+
+  // 停止监听目录变化
+  function stopLibraryWatch() {
+    unlistenLibrary.forEach(unlisten => unlisten());
+    unlistenLibrary = [];
+  }
+
+  // 打开漫画（文件夹和各类压缩包统一处理）
+  async function openComic(path: string, name: string) {
     isLoading.value = true;
     loadingProgress.value = 0;
     error.value = null;
 
     try {
-      const zipImages = await invoke<ZipImageInfo[]>("cmd_get_zip_images", {
-        path,
-      });
+      const opened = await invoke<OpenedComic>("cmd_open_comic", { path });
 
-      const images: ImageInfo[] = zipImages.map((img, index) => ({
-        index,
-        name: img.name,
-        path: img.path,
-      }));
+      let images: ImageInfo[];
 
-      // 批量获取图片尺寸
-      if (isDev) console.log('[漫画加载] 开始获取 ZIP 图片尺寸...');
-      const dimensionsPromises = images.map(async (img) => {
-        try {
-          const [width, height] = await invoke<[number, number]>("cmd_get_zip_image_dimensions", {
-            zipPath: path,
-            imagePath: img.path,
-          });
-          img.width = width;
-          img.height = height;
-        } catch (e) {
-          console.warn(`获取 ZIP 图片 ${img.path} 尺寸失败:`, e);
-          // 使用默认尺寸
-          img.width = 800;
-          img.height = 1200;
-        }
-      });
-      
-      await Promise.all(dimensionsPromises);
-      if (isDev) console.log('[漫画加载] ZIP 图片尺寸获取完成');
+      // 批量获取图片尺寸（后端只解析文件头，结果缓存在漫画库中）
+      if (isDev) console.log('[漫画加载] 开始获取图片尺寸...');
+      if (useSettingsStore().settings.spreadSplit !== "off") {
+        // 拆分跨页：页面列表由后端生成，跨页变为两个半页
+        const virtualPages = await invoke<VirtualPage[]>("cmd_get_virtual_pages", { comicPath: path });
+        images = virtualPages.map((virtual, i) => {
+          const page = opened.pages[virtual.pageIndex];
+          return {
+            index: i,
+            name: page.name,
+            path: page.path,
+            pageIndex: virtual.pageIndex,
+            half: virtual.half ?? undefined,
+            width: virtual.width ?? 800,
+            height: virtual.height ?? 1200,
+          };
+        });
+      } else {
+        const dimensions = await invoke<([number, number] | null)[]>("cmd_get_all_page_dimensions", {
+          comicPath: path,
+        });
+        images = opened.pages.map((page, i) => {
+          const size = dimensions[i];
+          if (!size) {
+            console.warn(`获取图片 ${page.path} 尺寸失败`);
+          }
+          // 获取失败时使用默认尺寸
+          return {
+            index: page.index,
+            name: page.name,
+            path: page.path,
+            pageIndex: page.index,
+            width: size?.[0] ?? 800,
+            height: size?.[1] ?? 1200,
+          };
+        });
+      }
+      if (isDev) console.log('[漫画加载] 图片尺寸获取完成');
 
       currentComic.value = {
         path,
         name,
-        isZip: true,
+        archiveKind: opened.metadata.archiveKind,
         imageCount: images.length,
         images,
       };
 
       return currentComic.value;
     } catch (e) {
-      error.value = String(e);
+      error.value = errorMessage(e);
       throw e;
     } finally {
       isLoading.value = false;
     }
   }
 
-  // 打开漫画（自动判断类型）
-  async function openComic(path: string, name: string, isZip: boolean) {
-    if (isZip) {
-      return openComicFromZip(path, name);
-    } else {
-      return openComicFromFolder(path, name);
+  // 写入当前漫画的 ComicInfo.xml（仅 ZIP/CBZ），写入后重新打开以刷新页面顺序
+  async function saveComicInfo(comicInfo: ComicInfoXml) {
+    if (!currentComic.value) {
+      throw new Error("没有打开的漫画");
     }
+
+    const { path, name } = currentComic.value;
+    await invoke<OpenedComic>("cmd_save_comic_info", { path, comicInfo });
+    return openComic(path, name);
   }
 
-  // 加载图片（使用 Blob URL 优化内存）
+  // 获取缩略图 Blob URL（pageIndex 省略时为封面），调用方负责 URL.revokeObjectURL
+  async function loadThumbnail(comicPath: string, maxSize: number, pageIndex?: number): Promise<string> {
+    const bytes = pageIndex === undefined
+      ? await invoke<number[]>("cmd_get_cover_thumbnail", { comicPath, maxSize })
+      : await invoke<number[]>("cmd_get_page_thumbnail", { comicPath, pageIndex, maxSize });
+    const blob = new Blob([new Uint8Array(bytes)], { type: "image/jpeg" });
+    return URL.createObjectURL(blob);
+  }
+
+  // 加载图片（返回 comic:// 页面地址，由 <img> 直接加载）
   async function loadImage(index: number): Promise<string> {
     const startTime = isDev ? performance.now() : 0;
     if (isDev) console.log(`[性能-Store] 开始加载图片 ${index}`);
@@ -268,84 +332,65 @@ export const useComicStore = defineStore("comic", () => {
     imageLoadingStates.value[index] = true;  // 标记为加载中
 
     try {
-      let blobUrl: string;
-
-      if (currentComic.value.isZip) {
-        // 从 ZIP 读取二进制数据
-        const invokeStart = isDev ? performance.now() : 0;
-        const bytes = await invoke<number[]>("cmd_read_zip_image_bytes", {
-          zipPath: currentComic.value.path,
-          imagePath: image.path,
-        });
-        const invokeEnd = isDev ? performance.now() : 0;
-        if (isDev) console.log(`[性能-Store] 图片 ${index} Rust读取耗时: ${(invokeEnd - invokeStart).toFixed(2)}ms, 大小: ${(bytes.length / 1024 / 1024).toFixed(2)}MB`);
-        
-        // 创建 Blob 和 URL
-        const blobStart = isDev ? performance.now() : 0;
-        const blob = new Blob([new Uint8Array(bytes)], { type: getMimeType(image.path) });
-        blobUrl = URL.createObjectURL(blob);
-        const blobEnd = isDev ? performance.now() : 0;
-        if (isDev) console.log(`[性能-Store] 图片 ${index} Blob创建耗时: ${(blobEnd - blobStart).toFixed(2)}ms`);
-      } else {
-        // 从文件读取二进制数据
-        const invokeStart = isDev ? performance.now() : 0;
-        const bytes = await invoke<number[]>("cmd_read_image_bytes", {
-          path: image.path,
-        });
-        const invokeEnd = isDev ? performance.now() : 0;
-        if (isDev) console.log(`[性能-Store] 图片 ${index} Rust读取耗时: ${(invokeEnd - invokeStart).toFixed(2)}ms, 大小: ${(bytes.length / 1024 / 1024).toFixed(2)}MB`);
-        
-        // 创建 Blob 和 URL
-        const blobStart = isDev ? performance.now() : 0;
-        const blob = new Blob([new Uint8Array(bytes)], { type: getMimeType(image.path) });
-        blobUrl = URL.createObjectURL(blob);
-        const blobEnd = isDev ? performance.now() : 0;
-        if (isDev) console.log(`[性能-Store] 图片 ${index} Blob创建耗时: ${(blobEnd - blobStart).toFixed(2)}ms`);
-      }
+      // 页面通过 comic:// 协议直接由 <img> 加载，不再经过 IPC 传输二进制数据
+      const url = pageUrl(currentComic.value.path, image);
 
-      // 再次检查是否已被取消（防止竞态条件）
-      if (!imageLoadingStates.value[index]) {
-        // 已被取消，释放刚创建的 Blob URL
-        URL.revokeObjectURL(blobUrl);
-        throw new Error(`图片 ${index} 加载已取消`);
-      }
-
-      // 缓存 Blob URL
-      currentComic.value.images[index].data = blobUrl;
+      // 缓存页面地址
+      currentComic.value.images[index].data = url;
 
       if (isDev) {
         const totalTime = performance.now() - startTime;
         console.log(`[性能-Store] 图片 ${index} 总耗时: ${totalTime.toFixed(2)}ms`);
       }
       
-      return blobUrl;
+      return url;
     } catch (e) {
-      throw new Error(`加载图片失败: ${e}`);
+      throw new Error(`加载图片失败: ${errorMessage(e)}`);
     } finally {
       delete imageLoadingStates.value[index];  // 清除加载状态
     }
   }
 
-  // 获取 MIME 类型
-  function getMimeType(path: string): string {
-    const ext = path.split('.').pop()?.toLowerCase();
-    switch (ext) {
-      case 'jpg':
-      case 'jpeg':
-        return 'image/jpeg';
-      case 'png':
-        return 'image/png';
-      case 'gif':
-        return 'image/gif';
-      case 'webp':
-        return 'image/webp';
-      case 'bmp':
-        return 'image/bmp';
-      case 'tiff':
-      case 'tif':
-        return 'image/tiff';
-      default:
-        return 'image/jpeg';
+  // 页面地址：comic://localhost/<编码后的漫画路径>/<页码>，
+  // 拆分的跨页附加 ?half=left|right，开启自动裁剪时附加 ?crop=1
+  function pageUrl(comicPath: string, image: ImageInfo): string {
+    const url = `${convertFileSrc(comicPath, "comic")}/${image.pageIndex}`;
+    if (image.half) {
+      return `${url}?half=${image.half}`;
+    }
+    return useSettingsStore().settings.autoCrop ? `${url}?crop=1` : url;
+  }
+
+  // 重新生成已加载页面的地址（如切换自动裁剪后），返回页码到新地址的映射
+  function refreshPageUrls(): Record<number, string> {
+    const urls: Record<number, string> = {};
+    if (!currentComic.value) return urls;
+
+    currentComic.value.images.forEach((image, index) => {
+      if (image.data && !image.data.startsWith("blob:")) {
+        image.data = pageUrl(currentComic.value!.path, image);
+        urls[index] = image.data;
+      }
+    });
+    return urls;
+  }
+
+  // 后台预读页面到后端内存缓存（count 省略时使用设置中的预加载数量）
+  async function prefetchPages(from: number, count?: number) {
+    if (!currentComic.value) return;
+
+    // 拆分跨页后按实际页码预读
+    const first = currentComic.value.images[from];
+    if (!first) return;
+
+    try {
+      await invoke("cmd_prefetch_pages", {
+        comicPath: currentComic.value.path,
+        from: first.pageIndex,
+        count: count ?? null,
+      });
+    } catch (e) {
+      if (isDev) console.warn("预读页面失败:", e);
     }
   }
 
@@ -447,12 +492,16 @@ export const useComicStore = defineStore("comic", () => {
     scanDirectory,
     removeFileTree,
     loadSavedDirectories,
-    openComicFromFolder,
-    openComicFromZip,
+    startLibraryWatch,
+    stopLibraryWatch,
     openComic,
+    saveComicInfo,
     loadImage,
+    loadThumbnail,
     preloadImages,
+    prefetchPages,
     evictImage,
+    refreshPageUrls,
     clearComic,
     clearAll,
   };
@@ -7,6 +7,8 @@ import type {
   ZoomMode,
   ReaderMode,
   AspectRatio,
+  SpreadSplit,
+  Language,
 } from "../types";
 import { defaultSettings } from "../types";
 import { usePreferredDark } from "@vueuse/core";
@@ -78,6 +80,36 @@ export const useSettingsStore = defineStore("settings", () => {
     saveSettings();
   }
 
+  // 设置页面内存缓存大小（MB）
+  function setPageCacheSize(sizeMb: number) {
+    settings.value.pageCacheSizeMb = Math.max(0, Math.min(4096, sizeMb));
+    saveSettings();
+  }
+
+  // 设置阅读进度的合并写入窗口（毫秒，0 为立即写入）
+  function setProgressSaveDelay(delayMs: number) {
+    settings.value.progressSaveDelayMs = Math.max(0, Math.min(10000, delayMs));
+    saveSettings();
+  }
+
+  // 设置是否自动裁剪页面边框
+  function setAutoCrop(enabled: boolean) {
+    settings.value.autoCrop = enabled;
+    saveSettings();
+  }
+
+  // 设置跨页拆分方式（重新打开漫画后生效）
+  function setSpreadSplit(split: SpreadSplit) {
+    settings.value.spreadSplit = split;
+    saveSettings();
+  }
+
+  // 设置后端消息（错误、警告）的语言，null 为跟随系统
+  function setLanguage(language: Language | null) {
+    settings.value.language = language;
+    saveSettings();
+  }
+
   // 设置阅读器模式
   function setReaderMode(mode: ReaderMode) {
     settings.value.readerMode = mode;
@@ -124,6 +156,11 @@ export const useSettingsStore = defineStore("settings", () => {
     setZoomMode,
     setCustomZoom,
     setPreloadCount,
+    setPageCacheSize,
+    setAutoCrop,
+    setSpreadSplit,
+    setProgressSaveDelay,
+    setLanguage,
     setReaderMode,
     setAspectRatio,
     setCustomAspectRatio,
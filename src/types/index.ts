@@ -1,20 +1,90 @@
+// 压缩包类型
+export type ArchiveKind = "zip" | "rar" | "7z" | "tar" | "pdf" | "epub";
+
 // 文件节点
 export interface FileNode {
   name: string;
   path: string;
   isDirectory: boolean;
   isComic: boolean;
-  isZip: boolean;
+  archiveKind: ArchiveKind | null;
   imageCount: number;
   children?: FileNode[];
 }
 
-// ZIP 图片信息
-export interface ZipImageInfo {
+// 漫画库条目（增量扫描索引）
+export interface LibraryEntry {
+  path: string;
+  name: string;
+  isDirectory: boolean;
+  isComic: boolean;
+  archiveKind: ArchiveKind | null;
+  pageCount: number;
+  size: number;
+  mtime: number;
+  scannedAt: number;
+}
+
+// ComicInfo 页面类型
+export type ComicPageType =
+  | "FrontCover"
+  | "InnerCover"
+  | "Roundup"
+  | "Story"
+  | "Advertisement"
+  | "Editorial"
+  | "Letters"
+  | "Preview"
+  | "BackCover"
+  | "Other"
+  | "Deleted";
+
+// ComicInfo 页面描述
+export interface ComicInfoPage {
+  image: number;
+  type: ComicPageType;
+  doublePage: boolean;
+}
+
+// ComicInfo.xml 元数据
+export interface ComicInfoXml {
+  series: string | null;
+  number: string | null;
+  volume: number | null;
+  title: string | null;
+  writer: string | null;
+  summary: string | null;
+  manga: string | null;
+  rightToLeft: boolean;
+  pages: ComicInfoPage[];
+}
+
+// 页面信息
+export interface PageInfo {
   index: number;
   name: string;
   path: string;
   size: number;
+  pageType: ComicPageType | null;
+  doublePage: boolean;
+}
+
+// 漫画元数据
+export interface ComicMetadata {
+  path: string;
+  name: string;
+  archiveKind: ArchiveKind | null;
+  pageCount: number;
+  title: string | null;
+  author: string | null;
+  rightToLeft: boolean;
+  comicInfo: ComicInfoXml | null;
+}
+
+// 打开漫画的结果
+export interface OpenedComic {
+  metadata: ComicMetadata;
+  pages: PageInfo[];
 }
 
 // 图片信息
@@ -22,26 +92,82 @@ export interface ImageInfo {
   index: number;
   name: string;
   path: string;
+  // 对应的实际页码（拆分跨页后与 index 不同）
+  pageIndex: number;
+  // 拆分跨页后对应的半边
+  half?: PageHalf;
   width?: number;
   height?: number;
   data?: string;
 }
 
-// 图片块（用于大图分块）
-export interface ImageChunk {
+// 分块位置（用于超长条漫）
+export interface TileRect {
   index: number;
   x: number;
   y: number;
   width: number;
   height: number;
-  data: string;
+}
+
+// 页面裁剪区域（cmd_get_page_crop）
+export interface CropBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+// 书本模式：单页 / 双页 / 双页（错开一页）
+export type SpreadMode = "single" | "double" | "double-offset";
+
+// 展开页（cmd_plan_spreads）：页码按显示顺序从左到右
+export interface Spread {
+  pages: number[];
+}
+
+// 跨页拆分方式：不拆分 / 先左后右 / 先右后左
+export type SpreadSplit = "off" | "ltr" | "rtl";
+
+// 跨页的半边
+export type PageHalf = "left" | "right";
+
+// 虚拟页（cmd_get_virtual_pages）：拆分后的跨页对应其中半边
+export interface VirtualPage {
+  pageIndex: number;
+  half: PageHalf | null;
+  width: number | null;
+  height: number | null;
+}
+
+// 页面缩放滤镜（cmd_read_page_scaled）
+export type ScaleFilter = "Lanczos3" | "CatmullRom" | "Triangle";
+
+// 分块输出格式：png / webp 为无损并保留透明通道
+export type TileFormat = "jpeg" | "png" | "webp";
+
+// 分块输出选项
+export interface TileOptions {
+  format?: TileFormat;
+  // JPEG 质量（1-100）
+  quality?: number;
+  // 不需要分块时直接返回原图数据
+  passthrough?: boolean;
+}
+
+// 分块布局：先获取布局占位，再通过 cmd_get_tile 按需加载各分块
+export interface TileLayout {
+  width: number;
+  height: number;
+  tileHeight: number;
+  tiles: TileRect[];
 }
 
 // 漫画信息
 export interface ComicInfo {
   path: string;
   name: string;
-  isZip: boolean;
+  archiveKind: ArchiveKind | null;
   imageCount: number;
   images: ImageInfo[];
 }
@@ -54,6 +180,7 @@ export interface ReadingProgress {
   lastReadTime: number;
   zoomMode?: ZoomMode;
   customZoom?: number;
+  spreadMode?: SpreadMode;
 }
 
 // 书签
@@ -75,6 +202,9 @@ export type ZoomMode = "fit-width" | "fit-height" | "original" | "custom";
 // 阅读器显示模式
 export type ReaderMode = "embedded" | "fullscreen";
 
+// 后端消息语言
+export type Language = "zh" | "en";
+
 // 图片比例预设
 export type AspectRatio =
   | "auto"
@@ -95,6 +225,12 @@ export interface Settings {
   aspectRatio: AspectRatio;
   customAspectWidth: number;
   customAspectHeight: number;
+  pageCacheSizeMb: number;
+  autoCrop: boolean;
+  spreadSplit: SpreadSplit;
+  progressSaveDelayMs: number;
+  // null 为跟随系统
+  language: Language | null;
 }
 
 // 打开的目录
@@ -105,12 +241,30 @@ export interface OpenedDirectory {
 
 // 应用数据
 export interface AppData {
+  version: number;
   settings: Settings;
   progress: Record<string, ReadingProgress>;
   bookmarks: Bookmark[];
   openedDirectories: OpenedDirectory[];
 }
 
+// 后端错误（命令失败时 invoke 抛出）
+export interface ComicError {
+  kind: "file" | "archive" | "comic" | "image" | "pdf" | "storage" | "internal";
+  code: string;
+  path: string | null;
+  source: string | null;
+  message: string;
+}
+
+// 取出错误的可显示描述，兼容后端错误对象和普通异常
+export function errorMessage(e: unknown): string {
+  if (e && typeof e === "object" && "message" in e) {
+    return String((e as { message: unknown }).message);
+  }
+  return String(e);
+}
+
 // 默认设置
 export const defaultSettings: Settings = {
   theme: "system",
@@ -121,4 +275,9 @@ export const defaultSettings: Settings = {
   aspectRatio: "auto",
   customAspectWidth: 3,
   customAspectHeight: 4,
+  pageCacheSizeMb: 256,
+  autoCrop: false,
+  spreadSplit: "off",
+  progressSaveDelayMs: 1000,
+  language: null,
 };